@@ -0,0 +1,316 @@
+use crate::backends::{
+    argv, native_arch, parse_dpkg_status, strip_native_arch, Action, PackageBackend, PackageChange,
+};
+use crate::exec::capture;
+
+// apt -------------------------------------------------------------------------
+pub struct Apt;
+
+pub(crate) const APT_SIMULATE: [&str; 4] = ["apt-get", "-s", "-o", "Debug::NoLocking=1"];
+
+impl PackageBackend for Apt {
+    fn name(&self) -> &'static str {
+        "apt"
+    }
+
+    fn binary(&self) -> &'static str {
+        "apt-get"
+    }
+
+    fn refresh(&self) -> Option<Vec<String>> {
+        Some(argv(&["apt-get", "update"]))
+    }
+
+    fn upgrade(&self) -> Vec<String> {
+        argv(&[
+            "apt-get",
+            "-y",
+            "-o",
+            "Dpkg::Options::=--force-confdef",
+            "-o",
+            "Dpkg::Options::=--force-confold",
+            "dist-upgrade",
+        ])
+    }
+
+    fn upgrade_packages(&self, packages: &[String]) -> Vec<String> {
+        let mut cmd = argv(&[
+            "apt-get",
+            "-y",
+            "-o",
+            "Dpkg::Options::=--force-confdef",
+            "-o",
+            "Dpkg::Options::=--force-confold",
+            "install",
+            "--only-upgrade",
+        ]);
+        cmd.extend(packages.iter().cloned());
+        cmd
+    }
+
+    fn cleanup(&self) -> Option<Vec<String>> {
+        Some(argv(&["apt-get", "-y", "autoremove", "--purge"]))
+    }
+
+    fn list_upgradable(&self) -> Result<Vec<PackageChange>, String> {
+        capture(&[&APT_SIMULATE[..], &["dist-upgrade"]].concat())
+            .map(|out| parse_apt_simulation(&out))
+            .ok_or_else(|| "apt-get dist-upgrade simulation failed".into())
+    }
+
+    fn list_orphans(&self) -> Result<Vec<PackageChange>, String> {
+        capture(&[&APT_SIMULATE[..], &["autoremove"]].concat())
+            .map(|out| parse_apt_simulation(&out))
+            .ok_or_else(|| "apt-get autoremove simulation failed".into())
+    }
+
+    fn installed(&self) -> Vec<(String, String)> {
+        let out = capture(&[
+            "dpkg-query",
+            "-W",
+            "-f",
+            "${db:Status-Abbrev}\t${binary:Package}\t${Version}\n",
+        ]);
+        let arch = native_arch();
+        out.unwrap_or_default()
+            .lines()
+            .filter_map(|line| {
+                let mut cols = line.split('\t');
+                let (status, name, version) = (cols.next()?, cols.next()?, cols.next()?);
+                (status.starts_with("ii") || status.starts_with("hi")).then(|| {
+                    (
+                        strip_native_arch(name, &arch).to_string(),
+                        version.to_string(),
+                    )
+                })
+            })
+            .collect()
+    }
+
+    fn hold_command(&self, packages: &[String], hold: bool) -> Option<Vec<String>> {
+        let mut cmd = argv(&["apt-mark", if hold { "hold" } else { "unhold" }]);
+        cmd.extend(packages.iter().cloned());
+        Some(cmd)
+    }
+
+    fn list_holds(&self) -> Result<Vec<String>, String> {
+        let out = capture(&["apt-mark", "showhold"]).ok_or("apt-mark showhold failed")?;
+        Ok(out
+            .lines()
+            .map(|l| l.trim().to_string())
+            .filter(|l| !l.is_empty())
+            .collect())
+    }
+
+    fn held_upgrades(&self, held: &[String]) -> Vec<PackageChange> {
+        if held.is_empty() {
+            return Vec::new();
+        }
+        let mut cmd = vec!["apt-cache", "policy"];
+        cmd.extend(held.iter().map(String::as_str));
+        parse_apt_policy(&capture(&cmd).unwrap_or_default())
+    }
+
+    fn has_security_channel(&self) -> bool {
+        true
+    }
+
+    // The simulation names each candidate's origins, e.g.
+    // "Inst libc6 [2.39-0ubuntu8] (2.39-0ubuntu8.3 Ubuntu:24.04/noble-security [amd64])".
+    fn list_security_upgrades(&self) -> Result<Vec<PackageChange>, String> {
+        let out = capture(&[&APT_SIMULATE[..], &["dist-upgrade"]].concat())
+            .ok_or("apt-get dist-upgrade simulation failed")?;
+        let security = apt_security_packages(&out);
+        Ok(parse_apt_simulation(&out)
+            .into_iter()
+            .filter(|c| c.action == Action::Upgrade && security.contains(&c.name))
+            .collect())
+    }
+
+    fn parse_transactions(&self, stdout: &str, _stderr: &str) -> Vec<PackageChange> {
+        parse_dpkg_status("apt", stdout, &native_arch())
+    }
+}
+
+pub(crate) fn apt_security_packages(simulation: &str) -> Vec<String> {
+    simulation
+        .lines()
+        .filter_map(|line| {
+            let rest = line.strip_prefix("Inst ")?;
+            let (_, origins) = rest.split_once('(')?;
+            let origins = origins.to_ascii_lowercase();
+            origins
+                .contains("-security")
+                .then(|| rest.split(' ').next().unwrap_or_default().to_string())
+        })
+        .collect()
+}
+
+// `apt-cache policy` blocks whose candidate differs from the installed version.
+pub(crate) fn parse_apt_policy(output: &str) -> Vec<PackageChange> {
+    let mut entries: Vec<(String, Option<String>, Option<String>)> = Vec::new();
+    for line in output.lines() {
+        if let Some(name) = line.strip_suffix(':').filter(|_| !line.starts_with(' ')) {
+            entries.push((name.to_string(), None, None));
+        } else if let Some(entry) = entries.last_mut() {
+            let line = line.trim();
+            if let Some(v) = line.strip_prefix("Installed: ") {
+                entry.1 = Some(v.to_string());
+            } else if let Some(v) = line.strip_prefix("Candidate: ") {
+                entry.2 = Some(v.to_string());
+            }
+        }
+    }
+    entries
+        .into_iter()
+        .filter_map(|(name, installed, candidate)| {
+            let (old, new) = (installed?, candidate?);
+            (old != new && old != "(none)" && new != "(none)").then_some(PackageChange {
+                backend: "apt",
+                name,
+                action: Action::Upgrade,
+                old_version: Some(old),
+                new_version: Some(new),
+            })
+        })
+        .collect()
+}
+
+// Parse `apt-get -s` output: "Inst pkg [old] (new repo [arch])" / "Remv pkg [old]"
+pub(crate) fn parse_apt_simulation(output: &str) -> Vec<PackageChange> {
+    let mut changes = Vec::new();
+    for line in output.lines() {
+        let (is_install, rest) = if let Some(rest) = line.strip_prefix("Inst ") {
+            (true, rest)
+        } else if let Some(rest) = line.strip_prefix("Remv ") {
+            (false, rest)
+        } else {
+            continue;
+        };
+
+        let mut parts = rest.splitn(2, ' ');
+        let name = parts.next().unwrap_or_default().to_string();
+        let tail = parts.next().unwrap_or_default();
+
+        let old_version = tail
+            .strip_prefix('[')
+            .and_then(|t| t.split(']').next())
+            .map(str::to_string);
+        let new_version = tail
+            .split_once('(')
+            .and_then(|(_, t)| t.split_whitespace().next())
+            .map(str::to_string);
+
+        let action = match (is_install, &old_version) {
+            (false, _) => Action::Remove,
+            (true, Some(_)) => Action::Upgrade,
+            (true, None) => Action::Install,
+        };
+        changes.push(PackageChange {
+            backend: "apt",
+            name,
+            action,
+            old_version,
+            new_version: if action == Action::Remove {
+                None
+            } else {
+                new_version
+            },
+        });
+    }
+    changes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::exec::testing::{install_fake, FakeRunner};
+    use crate::plan::plan_backend;
+
+    const SIMULATION: &str = "\
+Inst libc6 [2.39-0ubuntu8] (2.39-0ubuntu8.3 Ubuntu:24.04/noble-security [amd64])
+Inst hello [2.10-3] (2.10-3ubuntu1 Ubuntu:24.04/noble-updates [amd64])
+Inst libnew1 (1.0-1 Ubuntu:24.04/noble [amd64])
+Remv oldlib1 [0.9-2]
+";
+
+    #[test]
+    fn apt_plan_comes_from_the_simulation() {
+        install_fake(
+            FakeRunner::with_programs(&["apt-get"])
+                .on(&[&APT_SIMULATE[..], &["autoremove"]].concat(), &[(0, "")])
+                .on(
+                    &[&APT_SIMULATE[..], &["dist-upgrade"]].concat(),
+                    &[(0, SIMULATION)],
+                )
+                .on(&["apt-mark", "showhold"], &[(0, "")]),
+        );
+        let plan = plan_backend(&Apt, false);
+        let summary: Vec<(&str, Action)> = plan
+            .changes
+            .iter()
+            .map(|c| (c.name.as_str(), c.action))
+            .collect();
+        assert_eq!(
+            summary,
+            [
+                ("libc6", Action::Upgrade),
+                ("hello", Action::Upgrade),
+                ("libnew1", Action::Install),
+                ("oldlib1", Action::Remove),
+            ]
+        );
+        let security = Apt.list_security_upgrades().unwrap();
+        assert_eq!(security.len(), 1);
+        assert_eq!(security[0].new_version.as_deref(), Some("2.39-0ubuntu8.3"));
+    }
+
+    #[test]
+    fn apt_transactions_come_from_dpkg_output() {
+        let output = "\
+Preparing to unpack .../hello_2.10-3ubuntu1_amd64.deb ...
+Unpacking hello (2.10-3ubuntu1) over (2.10-3) ...
+Selecting previously unselected package libnew1:amd64.
+Unpacking libnew1:amd64 (1.0-1) ...
+Unpacking libc6:i386 (2.39-0ubuntu8.3) over (2.39-0ubuntu8) ...
+Removing oldlib1 (0.9-2) ...
+Removing obsolete conffile /etc/hello.conf ...
+Setting up hello (2.10-3ubuntu1) ...
+Purging configuration files for oldlib1 (0.9-2) ...
+";
+        install_fake(
+            FakeRunner::default().on(&["dpkg", "--print-architecture"], &[(0, "amd64\n")]),
+        );
+        let changes = Apt.parse_transactions(output, "");
+        let summary: Vec<(&str, Action, Option<&str>, Option<&str>)> = changes
+            .iter()
+            .map(|c| {
+                (
+                    c.name.as_str(),
+                    c.action,
+                    c.old_version.as_deref(),
+                    c.new_version.as_deref(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            summary,
+            [
+                (
+                    "hello",
+                    Action::Upgrade,
+                    Some("2.10-3"),
+                    Some("2.10-3ubuntu1")
+                ),
+                ("libnew1", Action::Install, None, Some("1.0-1")),
+                (
+                    "libc6:i386",
+                    Action::Upgrade,
+                    Some("2.39-0ubuntu8"),
+                    Some("2.39-0ubuntu8.3")
+                ),
+                ("oldlib1", Action::Remove, Some("0.9-2"), None),
+            ]
+        );
+    }
+}
@@ -0,0 +1,250 @@
+use crate::backends::{argv, upgrades_from_listing, Action, PackageBackend, PackageChange};
+use crate::exec::capture;
+use crate::summary::parse_size;
+
+// flatpak ---------------------------------------------------------------------
+// Packages are named by ID/ARCH/BRANCH, as a runtime such as
+// org.gnome.Platform is often installed in several branches at once.
+pub struct Flatpak;
+
+// "app/org.mozilla.firefox/x86_64/stable" → "org.mozilla.firefox/x86_64/stable".
+pub(crate) fn flatpak_pref(flatref: &str) -> &str {
+    flatref
+        .strip_prefix("app/")
+        .or_else(|| flatref.strip_prefix("runtime/"))
+        .unwrap_or(flatref)
+}
+
+impl PackageBackend for Flatpak {
+    fn name(&self) -> &'static str {
+        "flatpak"
+    }
+
+    fn binary(&self) -> &'static str {
+        "flatpak"
+    }
+
+    fn refresh(&self) -> Option<Vec<String>> {
+        Some(argv(&[
+            "flatpak",
+            "update",
+            "--appstream",
+            "--noninteractive",
+        ]))
+    }
+
+    fn upgrade(&self) -> Vec<String> {
+        argv(&["flatpak", "update", "-y", "--noninteractive"])
+    }
+
+    fn upgrade_packages(&self, packages: &[String]) -> Vec<String> {
+        let mut cmd = self.upgrade();
+        cmd.extend(packages.iter().cloned());
+        cmd
+    }
+
+    fn download_size(&self) -> Option<u64> {
+        let sizes = capture(&[
+            "flatpak",
+            "remote-ls",
+            "--updates",
+            "--columns=download-size",
+        ])?;
+        Some(
+            sizes
+                .lines()
+                .filter_map(|line| parse_size(line.trim()))
+                .sum(),
+        )
+    }
+
+    fn cleanup(&self) -> Option<Vec<String>> {
+        Some(argv(&[
+            "flatpak",
+            "uninstall",
+            "--unused",
+            "-y",
+            "--noninteractive",
+        ]))
+    }
+
+    fn list_upgradable(&self) -> Result<Vec<PackageChange>, String> {
+        let installed = self.installed();
+        let updates = capture(&["flatpak", "remote-ls", "--updates", "--columns=ref,version"])
+            .ok_or("flatpak remote-ls --updates failed")?;
+        let updates: String = updates
+            .lines()
+            .map(|line| format!("{}\n", flatpak_pref(line)))
+            .collect();
+        Ok(upgrades_from_listing("flatpak", &installed, &updates, 0))
+    }
+
+    fn list_orphans(&self) -> Result<Vec<PackageChange>, String> {
+        Err("flatpak cannot preview unused runtimes".into())
+    }
+
+    // Versions carry the remote and the deployed commit, e.g.
+    // "1.4.2 (flathub 3f2a…)", so a rollback can ask for the exact previous
+    // commit, and reinstall from the same remote what the run removed.
+    fn installed(&self) -> Vec<(String, String)> {
+        let out = capture(&["flatpak", "list", "--columns=ref,version,active:f,origin"]);
+        out.unwrap_or_default()
+            .lines()
+            .filter_map(|line| {
+                let mut cols = line.split('\t');
+                let (flatref, version, commit) = (cols.next()?, cols.next()?, cols.next()?);
+                let detail = format!("{} {commit}", cols.next().unwrap_or_default());
+                Some((
+                    flatpak_pref(flatref).to_string(),
+                    format!("{version} ({})", detail.trim()).trim().to_string(),
+                ))
+            })
+            .collect()
+    }
+
+    fn hold_command(&self, packages: &[String], hold: bool) -> Option<Vec<String>> {
+        let mut cmd = argv(&["flatpak", "mask"]);
+        if !hold {
+            cmd.push("--remove".into());
+        }
+        cmd.extend(packages.iter().cloned());
+        Some(cmd)
+    }
+
+    // `flatpak mask` prints one pattern per line, or a sentence when empty.
+    fn list_holds(&self) -> Result<Vec<String>, String> {
+        let out = capture(&["flatpak", "mask"]).ok_or("flatpak mask failed")?;
+        Ok(out
+            .lines()
+            .map(str::trim)
+            .filter(|l| !l.is_empty() && !l.contains(char::is_whitespace))
+            .map(str::to_string)
+            .collect())
+    }
+
+    fn held_upgrades(&self, held: &[String]) -> Vec<PackageChange> {
+        if held.is_empty() {
+            return Vec::new();
+        }
+        // Masks name application IDs (or patterns of them), not branches.
+        let masked = |name: &str| {
+            let app = name.split('/').next().unwrap_or(name);
+            held.iter().any(|pattern| match pattern.strip_suffix('*') {
+                Some(prefix) => app.starts_with(prefix),
+                None => app == pattern || name == pattern,
+            })
+        };
+        let mut upgrades = self.list_upgradable().unwrap_or_default();
+        upgrades.retain(|change| masked(&change.name));
+        upgrades
+    }
+
+    // With --noninteractive flatpak announces each operation on stdout,
+    // "Updating app/org.mozilla.firefox/x86_64/stable", and reports those
+    // that failed on stderr: "Warning: Failed to update app/…: reason".
+    fn parse_transactions(&self, stdout: &str, stderr: &str) -> Vec<PackageChange> {
+        let failed: Vec<&str> = stderr
+            .lines()
+            .filter_map(|line| line.split_once("Failed to "))
+            .filter_map(|(_, rest)| rest.split_whitespace().find(|w| w.contains('/')))
+            .map(|flatref| flatref.trim_end_matches(':'))
+            .collect();
+        stdout
+            .lines()
+            .filter_map(|line| {
+                let (action, flatref) = if let Some(r) = line.strip_prefix("Installing ") {
+                    (Action::Install, r.trim())
+                } else if let Some(r) = line.strip_prefix("Updating ") {
+                    (Action::Upgrade, r.trim())
+                } else if let Some(r) = line.strip_prefix("Uninstalling ") {
+                    (Action::Remove, r.trim())
+                } else {
+                    return None;
+                };
+                // Refs are KIND/ID/ARCH/BRANCH; this also skips bundle paths
+                // and "Updating appstream data for remote …".
+                let kind = flatref.split('/').next()?;
+                if !matches!(kind, "app" | "runtime") || failed.contains(&flatref) {
+                    return None;
+                }
+                Some(PackageChange {
+                    backend: "flatpak",
+                    name: flatpak_pref(flatref).to_string(),
+                    action,
+                    old_version: None,
+                    new_version: None,
+                })
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backends::{diff_inventory, inventory};
+    use crate::exec::testing::{install_fake, FakeRunner};
+
+    // flatpak update -y --noninteractive, with one operation failing.
+    const STDOUT: &str = "\
+Looking for updates…
+Updating appstream data for remote flathub
+Installing runtime/org.freedesktop.Platform.GL.default/x86_64/23.08
+Updating app/org.mozilla.firefox/x86_64/stable
+Updating runtime/org.gnome.Platform/x86_64/46
+Uninstalling runtime/org.gnome.Platform/x86_64/44
+";
+    const STDERR: &str = "\
+Warning: Failed to update runtime/org.gnome.Platform/x86_64/46: Could not connect to flathub
+";
+
+    #[test]
+    fn flatpak_transactions_come_from_the_quiet_output() {
+        let changes = Flatpak.parse_transactions(STDOUT, STDERR);
+        let summary: Vec<(&str, Action)> = changes
+            .iter()
+            .map(|c| (c.name.as_str(), c.action))
+            .collect();
+        assert_eq!(
+            summary,
+            [
+                (
+                    "org.freedesktop.Platform.GL.default/x86_64/23.08",
+                    Action::Install
+                ),
+                ("org.mozilla.firefox/x86_64/stable", Action::Upgrade),
+                ("org.gnome.Platform/x86_64/44", Action::Remove),
+            ]
+        );
+    }
+
+    #[test]
+    fn runtime_branches_are_separate_packages() {
+        let list = |platform_46: &str| {
+            format!(
+                "org.gnome.Platform/x86_64/44\t\t1a2b\tflathub\n\
+                 org.gnome.Platform/x86_64/46\t\t{platform_46}\tflathub\n"
+            )
+        };
+        install_fake(FakeRunner::with_programs(&["flatpak"]).on(
+            &["flatpak", "list"],
+            &[(0, &list("3c4d")), (0, &list("5e6f"))],
+        ));
+        let backends: Vec<Box<dyn PackageBackend>> = vec![Box::new(Flatpak)];
+        let before = inventory(&backends);
+        assert_eq!(before.len(), 2);
+        let changes = diff_inventory(&before, &inventory(&backends));
+        let summary: Vec<_> = changes
+            .iter()
+            .map(|c| (c.name.as_str(), c.action, c.old_version.as_deref()))
+            .collect();
+        assert_eq!(
+            summary,
+            [(
+                "org.gnome.Platform/x86_64/46",
+                Action::Upgrade,
+                Some("(flathub 3c4d)")
+            )]
+        );
+    }
+}
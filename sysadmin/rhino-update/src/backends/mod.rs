@@ -0,0 +1,288 @@
+use std::collections::BTreeMap;
+
+use crate::exec::{capture, command_exists, Step};
+
+mod apt;
+mod flatpak;
+mod pacstall;
+mod snap;
+
+pub use apt::Apt;
+pub use flatpak::Flatpak;
+pub use pacstall::Pacstall;
+pub use snap::Snap;
+
+// Package changes -------------------------------------------------------------
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Action {
+    Upgrade,
+    Install,
+    Remove,
+}
+
+impl Action {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Action::Upgrade => "upgrade",
+            Action::Install => "install",
+            Action::Remove => "remove",
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct PackageChange {
+    pub backend: &'static str,
+    pub name: String,
+    pub action: Action,
+    pub old_version: Option<String>,
+    pub new_version: Option<String>,
+}
+
+pub fn argv(parts: &[&str]) -> Vec<String> {
+    parts.iter().map(|s| s.to_string()).collect()
+}
+
+// Package backends ------------------------------------------------------------
+// Mutating operations return the argv to run so the caller can stream, time
+// and report them; queries run immediately and never touch the system.
+pub trait PackageBackend {
+    fn name(&self) -> &'static str;
+    fn binary(&self) -> &'static str;
+
+    fn is_available(&self) -> bool {
+        command_exists(self.binary())
+    }
+
+    // Refresh package metadata; None when the backend has no separate step.
+    fn refresh(&self) -> Option<Vec<String>>;
+    fn upgrade(&self) -> Vec<String>;
+    // Why upgrade() must not run as things stand, e.g. because it cannot
+    // leave held packages alone.
+    fn upgrade_blocked(&self) -> Option<String> {
+        None
+    }
+    // Bytes upgrade() will download, for backends whose output does not say.
+    fn download_size(&self) -> Option<u64> {
+        None
+    }
+    // Upgrade just these packages, leaving everything else alone.
+    fn upgrade_packages(&self, packages: &[String]) -> Vec<String>;
+    fn cleanup(&self) -> Option<Vec<String>>;
+
+    // Err carries a human-readable reason the query could not be answered.
+    fn list_upgradable(&self) -> Result<Vec<PackageChange>, String>;
+    fn list_orphans(&self) -> Result<Vec<PackageChange>, String>;
+
+    // Installed (name, version) pairs, used to diff what a step changed.
+    fn installed(&self) -> Vec<(String, String)> {
+        Vec::new()
+    }
+
+    // Command that adds (or releases) holds; None without native hold support.
+    fn hold_command(&self, _packages: &[String], _hold: bool) -> Option<Vec<String>> {
+        None
+    }
+
+    fn list_holds(&self) -> Result<Vec<String>, String> {
+        Err(format!("{} cannot hold packages", self.name()))
+    }
+
+    // Held packages that have a newer version available.
+    fn held_upgrades(&self, _held: &[String]) -> Vec<PackageChange> {
+        Vec::new()
+    }
+
+    // Whether upgrades can be told apart by a security origin (--security-only).
+    fn has_security_channel(&self) -> bool {
+        false
+    }
+
+    fn list_security_upgrades(&self) -> Result<Vec<PackageChange>, String> {
+        Err(format!("{} has no security channel", self.name()))
+    }
+
+    // The changes a step's output reports, in the order they happened.
+    fn parse_transactions(&self, _stdout: &str, _stderr: &str) -> Vec<PackageChange> {
+        Vec::new()
+    }
+}
+
+pub fn all_backends() -> Vec<Box<dyn PackageBackend>> {
+    vec![
+        Box::new(Apt),
+        Box::new(Pacstall),
+        Box::new(Flatpak),
+        Box::new(Snap),
+    ]
+}
+
+pub const BACKEND_NAMES: [&str; 4] = ["apt", "pacstall", "flatpak", "snap"];
+
+// Parse two-column "name version" listings into (name, version) pairs.
+pub(crate) fn parse_name_version(output: &str) -> Vec<(String, String)> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let mut cols = line.split_whitespace();
+            let name = cols.next()?.to_string();
+            Some((name, cols.next().unwrap_or_default().to_string()))
+        })
+        .collect()
+}
+
+// dpkg qualifies some package names with their architecture
+// ("libnew1:amd64") and not others. Names drop the native one wherever they
+// are read, so the inventory, dpkg's output and apt's simulation agree;
+// foreign packages keep theirs ("libc6:i386").
+pub(crate) fn native_arch() -> String {
+    capture(&["dpkg", "--print-architecture"])
+        .map(|arch| arch.trim().to_string())
+        .unwrap_or_default()
+}
+
+pub(crate) fn strip_native_arch<'a>(name: &'a str, arch: &str) -> &'a str {
+    name.strip_suffix(arch)
+        .and_then(|base| base.strip_suffix(':'))
+        .unwrap_or(name)
+}
+
+// dpkg's progress lines, printed by apt-get and by pacstall when it installs
+// the package it built: "Unpacking hello (2.10-3ubuntu1) over (2.10-3) ...",
+// "Unpacking libnew1:amd64 (1.0-1) ..." and "Removing oldlib1 (0.9-2) ...".
+// `arch` is the native architecture (see strip_native_arch).
+pub(crate) fn parse_dpkg_status(
+    backend: &'static str,
+    output: &str,
+    arch: &str,
+) -> Vec<PackageChange> {
+    let mut changes: Vec<PackageChange> = Vec::new();
+    for line in output.lines().map(str::trim) {
+        let (unpacking, rest) = if let Some(rest) = line.strip_prefix("Unpacking ") {
+            (true, rest)
+        } else if let Some(rest) = line.strip_prefix("Removing ") {
+            (false, rest)
+        } else {
+            continue;
+        };
+        // Also skips "Removing obsolete conffile …" and the like.
+        let Some((name, versions)) = rest.split_once(" (").filter(|(n, _)| !n.contains(' ')) else {
+            continue;
+        };
+        let name = strip_native_arch(name, arch);
+        // A retried step may unpack a package again; the first line has the
+        // version it started from.
+        if changes.iter().any(|c| c.name == name) {
+            continue;
+        }
+        let version = versions.split(')').next().map(str::to_string);
+        let over = versions
+            .split_once(" over (")
+            .and_then(|(_, old)| old.split(')').next())
+            .map(str::to_string);
+        let (action, old_version, new_version) = match (unpacking, over) {
+            (false, _) => (Action::Remove, version, None),
+            (true, Some(old)) => (Action::Upgrade, Some(old), version),
+            (true, None) => (Action::Install, None, version),
+        };
+        changes.push(PackageChange {
+            backend,
+            name: name.to_string(),
+            action,
+            old_version,
+            new_version,
+        });
+    }
+    changes
+}
+
+pub(crate) fn upgrades_from_listing(
+    backend: &'static str,
+    installed: &[(String, String)],
+    listing: &str,
+    header_lines: usize,
+) -> Vec<PackageChange> {
+    parse_name_version(listing)
+        .into_iter()
+        .skip(header_lines)
+        .map(|(name, new)| {
+            let old = installed
+                .iter()
+                .find(|(n, _)| *n == name)
+                .map(|(_, v)| v.clone());
+            PackageChange {
+                backend,
+                name,
+                action: Action::Upgrade,
+                old_version: old.filter(|v| !v.is_empty()),
+                new_version: Some(new).filter(|v| !v.is_empty()),
+            }
+        })
+        .collect()
+}
+
+// Installed-package inventory, used to work out what each step changed --------
+pub type Inventory = BTreeMap<(&'static str, String), String>;
+
+pub fn inventory(backends: &[Box<dyn PackageBackend>]) -> Inventory {
+    let mut inv = Inventory::new();
+    for backend in backends.iter().filter(|b| b.is_available()) {
+        for (name, version) in backend.installed() {
+            inv.insert((backend.name(), name), version);
+        }
+    }
+    inv
+}
+
+pub fn diff_inventory(before: &Inventory, after: &Inventory) -> Vec<PackageChange> {
+    let mut changes = Vec::new();
+    for ((backend, name), new) in after {
+        let action = match before.get(&(*backend, name.clone())) {
+            Some(old) if old == new => continue,
+            Some(_) => Action::Upgrade,
+            None => Action::Install,
+        };
+        changes.push(PackageChange {
+            backend,
+            name: name.clone(),
+            action,
+            old_version: before.get(&(*backend, name.clone())).cloned(),
+            new_version: Some(new.clone()),
+        });
+    }
+    for ((backend, name), old) in before {
+        if !after.contains_key(&(*backend, name.clone())) {
+            changes.push(PackageChange {
+                backend,
+                name: name.clone(),
+                action: Action::Remove,
+                old_version: Some(old.clone()),
+                new_version: None,
+            });
+        }
+    }
+    changes
+}
+
+// The packages a backend step changed according to its own output, with the
+// versions that output leaves out (flatpak, snap) taken from the inventory
+// diff.
+pub fn step_transactions(backend: &dyn PackageBackend, step: &Step) -> Vec<PackageChange> {
+    let mut changes = backend.parse_transactions(&step.output, &step.stderr_tail);
+    for change in &mut changes {
+        let Some(diff) = step
+            .packages
+            .iter()
+            .find(|c| c.backend == change.backend && c.name == change.name)
+        else {
+            continue;
+        };
+        if change.old_version.is_none() {
+            change.old_version = diff.old_version.clone();
+        }
+        if change.new_version.is_none() {
+            change.new_version = diff.new_version.clone();
+        }
+    }
+    changes
+}
@@ -0,0 +1,139 @@
+use crate::backends::{
+    argv, native_arch, parse_dpkg_status, Action, PackageBackend, PackageChange,
+};
+use crate::exec::capture;
+use crate::holds::pacstall_holds;
+
+// pacstall --------------------------------------------------------------------
+pub struct Pacstall;
+
+// `pacstall -Up` cannot skip packages, and which ones have a newer build is
+// only known once it runs; `pacstall -I` on the rest would rebuild every one
+// of them. So any hold pauses all of pacstall's upgrades.
+pub(crate) fn blocked_by_holds(held: &[String]) -> Option<String> {
+    (!held.is_empty()).then(|| {
+        format!(
+            "pacstall upgrades all its packages at once and cannot skip held ones ({}), \
+             so none were upgraded; release the holds (rhino-update hold remove pacstall:…) \
+             to upgrade them",
+            held.join(", ")
+        )
+    })
+}
+
+impl PackageBackend for Pacstall {
+    fn name(&self) -> &'static str {
+        "pacstall"
+    }
+
+    fn binary(&self) -> &'static str {
+        "pacstall"
+    }
+
+    fn refresh(&self) -> Option<Vec<String>> {
+        Some(argv(&["pacstall", "-U"]))
+    }
+
+    fn upgrade(&self) -> Vec<String> {
+        argv(&["pacstall", "-P", "-Up"])
+    }
+
+    fn upgrade_blocked(&self) -> Option<String> {
+        blocked_by_holds(&pacstall_holds())
+    }
+
+    fn upgrade_packages(&self, packages: &[String]) -> Vec<String> {
+        let mut cmd = argv(&["pacstall", "-P", "-I"]);
+        cmd.extend(packages.iter().cloned());
+        cmd
+    }
+
+    // pacstall packages are dpkg packages; apt's autoremove covers them.
+    fn cleanup(&self) -> Option<Vec<String>> {
+        None
+    }
+
+    // pacstall has no simulation mode; say what `pacstall -Up` will examine.
+    fn list_upgradable(&self) -> Result<Vec<PackageChange>, String> {
+        let installed = capture(&["pacstall", "-L"])
+            .map(|out| out.lines().filter(|l| !l.trim().is_empty()).count())
+            .unwrap_or(0);
+        Err(format!(
+            "pacstall cannot simulate upgrades; {installed} installed package(s) will be checked"
+        ))
+    }
+
+    fn list_orphans(&self) -> Result<Vec<PackageChange>, String> {
+        Ok(Vec::new())
+    }
+
+    fn list_holds(&self) -> Result<Vec<String>, String> {
+        Ok(pacstall_holds())
+    }
+
+    // Without a simulation every installed held package may have an upgrade
+    // waiting; they are listed at their current version.
+    fn held_upgrades(&self, held: &[String]) -> Vec<PackageChange> {
+        if held.is_empty() {
+            return Vec::new();
+        }
+        let own = capture(&["pacstall", "-L"]).unwrap_or_default();
+        held.iter()
+            .filter(|pkg| own.lines().any(|line| line.trim() == pkg.as_str()))
+            .map(|pkg| PackageChange {
+                backend: "pacstall",
+                name: pkg.clone(),
+                action: Action::Upgrade,
+                old_version: capture(&["dpkg-query", "-W", "-f=${Version}", pkg])
+                    .filter(|v| !v.is_empty()),
+                new_version: None,
+            })
+            .collect()
+    }
+
+    // pacstall installs what it built through dpkg, along with any apt
+    // dependencies; only the packages it lists as its own are pacstall's.
+    fn parse_transactions(&self, stdout: &str, _stderr: &str) -> Vec<PackageChange> {
+        let own = capture(&["pacstall", "-L"]).unwrap_or_default();
+        let own: Vec<&str> = own.lines().map(str::trim).collect();
+        let mut changes = parse_dpkg_status("apt", stdout, &native_arch());
+        for change in &mut changes {
+            if own.contains(&change.name.as_str()) {
+                change.backend = "pacstall";
+            }
+        }
+        changes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::exec::testing::{install_fake, FakeRunner};
+    use crate::holds::held_back_status;
+
+    #[test]
+    fn any_hold_pauses_the_whole_upgrade() {
+        assert_eq!(blocked_by_holds(&[]), None);
+        let note = blocked_by_holds(&["neofetch".into()]).unwrap();
+        assert!(note.contains("(neofetch)"), "{note}");
+        assert!(note.contains("none were upgraded"), "{note}");
+    }
+
+    #[test]
+    fn held_packages_are_reported_without_a_known_upgrade() {
+        install_fake(
+            FakeRunner::default()
+                .on(
+                    &["pacstall", "-L"],
+                    &[(0, "neofetch\nvisual-studio-code-bin\n")],
+                )
+                .on(&["dpkg-query"], &[(0, "7.1.0-1")]),
+        );
+        let held = Pacstall.held_upgrades(&["neofetch".into(), "not-installed".into()]);
+        assert_eq!(held.len(), 1);
+        assert_eq!(held[0].name, "neofetch");
+        assert_eq!(held[0].old_version.as_deref(), Some("7.1.0-1"));
+        assert_eq!(held_back_status(&held[0]), "held, upgrade status unknown");
+    }
+}
@@ -0,0 +1,151 @@
+use crate::backends::{argv, upgrades_from_listing, Action, PackageBackend, PackageChange};
+use crate::exec::capture;
+
+// snap ------------------------------------------------------------------------
+pub struct Snap;
+
+impl PackageBackend for Snap {
+    fn name(&self) -> &'static str {
+        "snap"
+    }
+
+    fn binary(&self) -> &'static str {
+        "snap"
+    }
+
+    // snapd keeps its own metadata current.
+    fn refresh(&self) -> Option<Vec<String>> {
+        None
+    }
+
+    fn upgrade(&self) -> Vec<String> {
+        argv(&["snap", "refresh"])
+    }
+
+    fn upgrade_packages(&self, packages: &[String]) -> Vec<String> {
+        let mut cmd = self.upgrade();
+        cmd.extend(packages.iter().cloned());
+        cmd
+    }
+
+    fn cleanup(&self) -> Option<Vec<String>> {
+        None
+    }
+
+    // `snap refresh --list` exits non-zero when snapd is unreachable and prints
+    // "All snaps up to date." on stderr when there is nothing to do.
+    fn list_upgradable(&self) -> Result<Vec<PackageChange>, String> {
+        let installed = self.installed();
+        let updates =
+            capture(&["snap", "refresh", "--list"]).ok_or("snap refresh --list failed")?;
+        Ok(upgrades_from_listing("snap", &installed, &updates, 1))
+    }
+
+    fn list_orphans(&self) -> Result<Vec<PackageChange>, String> {
+        Ok(Vec::new())
+    }
+
+    // Versions carry the revision, e.g. "120.0-2 (4356)", so a rollback can
+    // reinstall the exact previous one.
+    fn installed(&self) -> Vec<(String, String)> {
+        let out = capture(&["snap", "list"]).unwrap_or_default();
+        out.lines()
+            .skip(1)
+            .filter_map(|line| {
+                let mut cols = line.split_whitespace();
+                let (name, version, rev) = (cols.next()?, cols.next()?, cols.next()?);
+                Some((name.to_string(), format!("{version} ({rev})")))
+            })
+            .collect()
+    }
+
+    fn hold_command(&self, packages: &[String], hold: bool) -> Option<Vec<String>> {
+        let mut cmd = argv(&[
+            "snap",
+            "refresh",
+            if hold { "--hold=forever" } else { "--unhold" },
+        ]);
+        cmd.extend(packages.iter().cloned());
+        Some(cmd)
+    }
+
+    // Held snaps say so in the Notes column of `snap list`.
+    fn list_holds(&self) -> Result<Vec<String>, String> {
+        let out = capture(&["snap", "list"]).ok_or("snap list failed")?;
+        Ok(out
+            .lines()
+            .skip(1)
+            .filter(|line| {
+                line.split_whitespace()
+                    .last()
+                    .is_some_and(|notes| notes.split(',').any(|n| n == "held"))
+            })
+            .filter_map(|line| line.split_whitespace().next())
+            .map(str::to_string)
+            .collect())
+    }
+
+    // `snap refresh --list` still lists held snaps; a plain refresh skips them.
+    fn held_upgrades(&self, held: &[String]) -> Vec<PackageChange> {
+        if held.is_empty() {
+            return Vec::new();
+        }
+        let mut upgrades = self.list_upgradable().unwrap_or_default();
+        upgrades.retain(|change| held.contains(&change.name));
+        upgrades
+    }
+
+    // "firefox 120.0-2 from Mozilla✓ refreshed", or
+    // "firefox (beta) 121.0b3 from Mozilla✓ refreshed" off the stable channel.
+    fn parse_transactions(&self, stdout: &str, _stderr: &str) -> Vec<PackageChange> {
+        stdout
+            .lines()
+            .filter(|line| line.trim_end().ends_with(" refreshed"))
+            .filter_map(|line| {
+                let mut cols = line.split_whitespace().peekable();
+                let name = cols.next()?;
+                cols.next_if(|col| col.starts_with('('));
+                Some(PackageChange {
+                    backend: "snap",
+                    name: name.to_string(),
+                    action: Action::Upgrade,
+                    old_version: None,
+                    new_version: cols.next().map(str::to_string),
+                })
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::exec::testing::{install_fake, FakeRunner};
+
+    const SNAP_LIST: &str = "\
+Name      Version    Rev    Tracking       Publisher   Notes
+core22    20240111   1122   latest/stable  canonical✓  base
+firefox   120.0-2    3358   latest/stable  mozilla✓    held
+";
+
+    const REFRESH_LIST: &str = "\
+Name     Version   Rev   Size   Publisher  Notes
+core22   20240208  1168  77MB   canonical✓ -
+firefox  121.0-1   3407  250MB  mozilla✓   -
+";
+
+    #[test]
+    fn held_snaps_with_a_refresh_are_held_back() {
+        install_fake(
+            FakeRunner::default()
+                .on(&["snap", "list"], &[(0, SNAP_LIST)])
+                .on(&["snap", "refresh", "--list"], &[(0, REFRESH_LIST)]),
+        );
+        assert_eq!(Snap.list_holds(), Ok(vec!["firefox".to_string()]));
+        let held = Snap.held_upgrades(&["firefox".to_string()]);
+        assert_eq!(held.len(), 1);
+        assert_eq!(held[0].name, "firefox");
+        assert_eq!(held[0].old_version.as_deref(), Some("120.0-2 (3358)"));
+        assert_eq!(held[0].new_version.as_deref(), Some("121.0-1"));
+    }
+}
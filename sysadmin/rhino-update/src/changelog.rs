@@ -0,0 +1,487 @@
+use std::fs;
+use std::path::Path;
+
+use crate::backends::{Action, PackageBackend, PackageChange};
+use crate::cli::Options;
+use crate::exec::capture;
+use crate::history::format_change_versions;
+use crate::json::{change_from_json, parse_json, Json};
+use crate::reboot::version_cmp;
+use crate::report::write_report;
+use crate::rollback::{cached_deb, recorded_net_changes};
+use crate::store::load_run;
+use crate::ui::{BLUE, BOLD, CYAN, GREEN, MAGENTA, RED, YELLOW};
+
+// Changelogs and CVEs ---------------------------------------------------------
+// Debian changelogs come from the new version's .deb when apt has it cached,
+// otherwise from the installed /usr/share/doc copy once that covers the new
+// version, and otherwise, for upgrades still pending, from `apt-get
+// changelog` (the archive's changelog server). `--cve-feed` adds an Ubuntu
+// USN database (JSON: {"USN-…": {"cves": [...], "releases": {codename:
+// {"binaries": {pkg: {"version": v}}}}}}), matched by fixed version.
+#[derive(Clone, Debug)]
+pub(crate) struct ChangelogEntry {
+    pub(crate) version: String,
+    pub(crate) text: String,
+}
+
+pub(crate) fn read_changelog(name: &str, version: &str) -> Option<String> {
+    let base = name.split(':').next().unwrap_or(name);
+    if let Some(deb) = cached_deb(name, version) {
+        let member = format!("./usr/share/doc/{base}/changelog.Debian.gz");
+        let script = r#"dpkg-deb --fsys-tarfile "$1" | tar -xOf - "$2" | gzip -dc"#;
+        let deb = deb.display().to_string();
+        if let Some(text) = capture(&["sh", "-c", script, "sh", &deb, &member]) {
+            if !text.is_empty() {
+                return Some(text);
+            }
+        }
+    }
+    let doc = Path::new("/usr/share/doc").join(base);
+    let installed = ["changelog.Debian.gz", "changelog.gz"]
+        .iter()
+        .map(|file| doc.join(file))
+        .find(|path| path.is_file())
+        .and_then(|path| capture(&["gzip", "-dc", &path.display().to_string()]))
+        .or_else(|| fs::read_to_string(doc.join("changelog.Debian")).ok());
+    let covers = |text: &str| parse_changelog(text).iter().any(|e| e.version == version);
+    if installed.as_deref().is_some_and(covers) {
+        return installed;
+    }
+    capture(&["apt-get", "-qq", "changelog", &format!("{name}={version}")])
+        .filter(|text| !text.trim().is_empty())
+        .or(installed)
+}
+
+// Entries start with an unindented "package (version) suite; urgency=…" line.
+pub(crate) fn parse_changelog(text: &str) -> Vec<ChangelogEntry> {
+    let mut entries: Vec<ChangelogEntry> = Vec::new();
+    for line in text.lines() {
+        let version = line
+            .split_once(" (")
+            .filter(|_| !line.starts_with(char::is_whitespace))
+            .and_then(|(_, rest)| rest.split_once(')'))
+            .map(|(version, _)| version.to_string());
+        match (version, entries.last_mut()) {
+            (Some(version), _) => entries.push(ChangelogEntry {
+                version,
+                text: format!("{line}\n"),
+            }),
+            (None, Some(entry)) => {
+                entry.text.push_str(line);
+                entry.text.push('\n');
+            }
+            (None, None) => {}
+        }
+    }
+    entries
+}
+
+// The entries an upgrade brings in: newer than `old`, up to `new`.
+pub(crate) fn changelog_between(name: &str, old: &str, new: &str) -> Option<Vec<ChangelogEntry>> {
+    let entries = parse_changelog(&read_changelog(name, new)?);
+    Some(
+        entries
+            .into_iter()
+            .filter(|e| {
+                version_cmp(&e.version, old).is_gt() && version_cmp(&e.version, new).is_le()
+            })
+            .collect(),
+    )
+}
+
+pub(crate) fn cve_ids(text: &str) -> Vec<String> {
+    let mut ids: Vec<String> = text
+        .match_indices("CVE-")
+        .filter_map(|(i, _)| {
+            let rest = &text[i + 4..];
+            let len = rest
+                .find(|c: char| !(c.is_ascii_digit() || c == '-'))
+                .unwrap_or(rest.len());
+            let (year, number) = rest[..len].trim_end_matches('-').split_once('-')?;
+            (year.len() == 4 && number.len() >= 4 && !number.contains('-'))
+                .then(|| format!("CVE-{year}-{number}"))
+        })
+        .collect();
+    ids.sort_by(|a, b| version_cmp(a, b));
+    ids.dedup();
+    ids
+}
+
+pub(crate) const CHANGELOG_EXCERPT_LINES: usize = 20;
+
+#[derive(Debug)]
+pub(crate) struct PackageChangelog {
+    pub(crate) change: PackageChange,
+    pub(crate) entries: Vec<ChangelogEntry>,
+}
+
+impl From<&PackageChangelog> for Json {
+    fn from(log: &PackageChangelog) -> Json {
+        let entries = log
+            .entries
+            .iter()
+            .map(|e| {
+                Json::obj([
+                    ("version", e.version.as_str().into()),
+                    ("text", e.text.as_str().into()),
+                ])
+            })
+            .collect();
+        Json::obj([
+            ("backend", log.change.backend.into()),
+            ("name", log.change.name.as_str().into()),
+            ("action", log.change.action.as_str().into()),
+            ("old_version", log.change.old_version.clone().into()),
+            ("new_version", log.change.new_version.clone().into()),
+            ("entries", Json::Arr(entries)),
+        ])
+    }
+}
+
+pub(crate) fn collect_changelogs(changes: &[PackageChange]) -> Vec<PackageChangelog> {
+    changes
+        .iter()
+        .filter(|c| c.backend == "apt" && c.action == Action::Upgrade)
+        .filter_map(|change| {
+            let (old, new) = (
+                change.old_version.as_deref()?,
+                change.new_version.as_deref()?,
+            );
+            Some(PackageChangelog {
+                change: change.clone(),
+                entries: changelog_between(&change.name, old, new).unwrap_or_default(),
+            })
+        })
+        .collect()
+}
+
+// The first CHANGELOG_EXCERPT_LINES lines per package; the report keeps all.
+pub(crate) fn print_changelogs(logs: &[PackageChangelog]) {
+    color_print!(format!("{BLUE}{BOLD}"), "📜 Changelogs\n");
+    for log in logs {
+        color_print!(
+            BOLD,
+            "\n  {} {}\n",
+            log.change.name,
+            format_change_versions(&log.change)
+        );
+        if log.entries.is_empty() {
+            color_print!(YELLOW, "    no local changelog covers this upgrade\n");
+            continue;
+        }
+        let lines: Vec<&str> = log
+            .entries
+            .iter()
+            .flat_map(|e| e.text.lines())
+            .filter(|l| !l.trim().is_empty())
+            .collect();
+        for line in lines.iter().take(CHANGELOG_EXCERPT_LINES) {
+            color_print!("", "    {}\n", line);
+        }
+        if lines.len() > CHANGELOG_EXCERPT_LINES {
+            color_print!(
+                CYAN,
+                "    … {} more line(s) in the run report\n",
+                lines.len() - CHANGELOG_EXCERPT_LINES
+            );
+        }
+    }
+    if logs.is_empty() {
+        color_print!(GREEN, "  no apt packages were upgraded\n");
+    }
+    color_print!("", "\n");
+}
+
+pub(crate) fn changelogs_from_json(run: &Json) -> Vec<PackageChangelog> {
+    run.get("changelogs")
+        .as_arr()
+        .iter()
+        .filter_map(|log| {
+            Some(PackageChangelog {
+                change: change_from_json(log)?,
+                entries: log
+                    .get("entries")
+                    .as_arr()
+                    .iter()
+                    .filter_map(|e| {
+                        Some(ChangelogEntry {
+                            version: e.get("version").as_str()?.to_string(),
+                            text: e.get("text").as_str()?.to_string(),
+                        })
+                    })
+                    .collect(),
+            })
+        })
+        .collect()
+}
+
+#[derive(Debug)]
+pub(crate) struct CveFinding {
+    pub(crate) change: PackageChange,
+    pub(crate) cves: Vec<String>,
+    pub(crate) notices: Vec<String>,
+    // Whether a changelog covering the new version was found.
+    pub(crate) changelog: bool,
+}
+
+impl From<&CveFinding> for Json {
+    fn from(finding: &CveFinding) -> Json {
+        let strings =
+            |items: &[String]| Json::Arr(items.iter().map(|s| s.as_str().into()).collect());
+        Json::obj([
+            ("backend", finding.change.backend.into()),
+            ("name", finding.change.name.as_str().into()),
+            ("old_version", finding.change.old_version.clone().into()),
+            ("new_version", finding.change.new_version.clone().into()),
+            ("cves", strings(&finding.cves)),
+            ("notices", strings(&finding.notices)),
+            ("changelog", Json::Bool(finding.changelog)),
+        ])
+    }
+}
+
+// USN notices whose fixed version for `name` falls within (old, new].
+pub(crate) fn feed_matches(
+    feed: &Json,
+    name: &str,
+    old: &str,
+    new: &str,
+) -> Vec<(String, Vec<String>)> {
+    let Json::Obj(notices) = feed else {
+        return Vec::new();
+    };
+    let base = name.split(':').next().unwrap_or(name);
+    notices
+        .iter()
+        .filter(|(_, notice)| {
+            let Json::Obj(releases) = notice.get("releases") else {
+                return false;
+            };
+            releases.iter().any(|(_, release)| {
+                ["binaries", "sources"].iter().any(|kind| {
+                    release
+                        .get(kind)
+                        .get(base)
+                        .get("version")
+                        .as_str()
+                        .is_some_and(|fixed| {
+                            version_cmp(fixed, old).is_gt() && version_cmp(fixed, new).is_le()
+                        })
+                })
+            })
+        })
+        .map(|(id, notice)| {
+            let cves = notice.get("cves").as_arr().iter().filter_map(Json::as_str);
+            (
+                id.clone(),
+                cves.filter(|c| c.starts_with("CVE-"))
+                    .map(str::to_string)
+                    .collect(),
+            )
+        })
+        .collect()
+}
+
+pub(crate) fn load_cve_feed(path: &str) -> Result<Json, String> {
+    let text = fs::read_to_string(path).map_err(|e| format!("{path}: {e}"))?;
+    parse_json(&text).map_err(|e| format!("{path}: {e}"))
+}
+
+pub(crate) fn find_cves(changes: &[PackageChange], feed: Option<&Json>) -> Vec<CveFinding> {
+    changes
+        .iter()
+        .filter(|c| c.backend == "apt" && c.action == Action::Upgrade)
+        .filter_map(|change| {
+            let (old, new) = (
+                change.old_version.as_deref()?,
+                change.new_version.as_deref()?,
+            );
+            let entries = changelog_between(&change.name, old, new);
+            let mut finding = CveFinding {
+                change: change.clone(),
+                cves: entries
+                    .iter()
+                    .flatten()
+                    .flat_map(|e| cve_ids(&e.text))
+                    .collect(),
+                notices: Vec::new(),
+                changelog: entries.as_ref().is_some_and(|e| !e.is_empty()),
+            };
+            for (notice, cves) in feed
+                .map(|f| feed_matches(f, &change.name, old, new))
+                .unwrap_or_default()
+            {
+                finding.notices.push(notice);
+                finding.cves.extend(cves);
+            }
+            finding.cves.sort_by(|a, b| version_cmp(a, b));
+            finding.cves.dedup();
+            Some(finding)
+        })
+        .collect()
+}
+
+pub fn cmd_cves(opts: &Options, backends: &[Box<dyn PackageBackend>], run_id: Option<&str>) -> i32 {
+    let (scope, changes) = match run_id {
+        None | Some("pending") => {
+            let changes = backends
+                .iter()
+                .filter(|b| b.is_available())
+                .filter_map(|b| b.list_upgradable().ok())
+                .flatten()
+                .collect();
+            ("pending".to_string(), changes)
+        }
+        Some(id) => match load_run((id != "last").then_some(id), Some("update")) {
+            Ok(run) => (
+                run.get("run_id").as_str().unwrap_or(id).to_string(),
+                recorded_net_changes(&run),
+            ),
+            Err(msg) => {
+                color_print!(RED, "❌ Cannot load run: {}\n", msg);
+                return 1;
+            }
+        },
+    };
+    let feed = match opts.cve_feed.as_deref().map(load_cve_feed).transpose() {
+        Ok(feed) => feed,
+        Err(msg) => {
+            color_print!(RED, "❌ Cannot read CVE feed: {}\n", msg);
+            return 1;
+        }
+    };
+    let findings = find_cves(&changes, feed.as_ref());
+
+    let total: usize = findings.iter().map(|f| f.cves.len()).sum();
+    color_print!(
+        format!("{MAGENTA}{BOLD}"),
+        "🛡  CVEs fixed by {} ({} package(s), {} CVE reference(s))\n\n",
+        if scope == "pending" {
+            "pending upgrades".to_string()
+        } else {
+            format!("run {scope}")
+        },
+        findings.len(),
+        total
+    );
+    print_cves(&findings);
+    if findings.is_empty() {
+        color_print!(GREEN, "  no apt upgrades to examine\n");
+    }
+    if opts.report {
+        let json = Json::obj([
+            ("scope", scope.as_str().into()),
+            ("cve_feed", opts.cve_feed.clone().into()),
+            (
+                "packages",
+                Json::Arr(findings.iter().map(Json::from).collect()),
+            ),
+        ]);
+        write_report(&json, opts);
+    }
+    0
+}
+
+pub(crate) fn print_cves<'a>(findings: impl IntoIterator<Item = &'a CveFinding>) {
+    for finding in findings {
+        color_print!(
+            BOLD,
+            "  {} {}\n",
+            finding.change.name,
+            format_change_versions(&finding.change)
+        );
+        if finding.cves.is_empty() {
+            let why = if finding.changelog || !finding.notices.is_empty() {
+                "no CVE references"
+            } else {
+                "no changelog for the new version available"
+            };
+            color_print!(YELLOW, "      {}\n", why);
+        } else {
+            color_print!(RED, "      {}\n", finding.cves.join(" "));
+        }
+        if !finding.notices.is_empty() {
+            color_print!(CYAN, "      {}\n", finding.notices.join(" "));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::exec::testing::{install_fake, FakeRunner};
+
+    #[test]
+    fn cve_ids_are_validated_sorted_and_unique() {
+        let text = "\
+  * SECURITY UPDATE: overflow (CVE-2024-10003, CVE-2023-4567)
+    - CVE-2024-9999 and again CVE-2023-4567.
+    - not CVE-24-1234, CVE-2024-12 or CVE-2024-1234-5
+";
+        assert_eq!(
+            cve_ids(text),
+            ["CVE-2023-4567", "CVE-2024-9999", "CVE-2024-10003"]
+        );
+    }
+
+    #[test]
+    fn pending_upgrades_read_the_candidate_changelog_from_apt() {
+        let changelog = "\
+rhino-test-tool (1.0-3) noble-security; urgency=medium
+
+  * SECURITY UPDATE: path traversal (CVE-2026-1234)
+
+ -- Rhino Maintainers <dev@rhinolinux.org>  Thu, 15 Oct 2026 10:00:00 +0000
+
+rhino-test-tool (1.0-2) noble; urgency=medium
+
+  * Fix CVE-2025-0001.
+
+ -- Rhino Maintainers <dev@rhinolinux.org>  Mon, 01 Jun 2026 10:00:00 +0000
+";
+        let fake = install_fake(
+            FakeRunner::default().on(&["apt-get", "-qq", "changelog"], &[(0, changelog)]),
+        );
+        let change = PackageChange {
+            backend: "apt",
+            name: "rhino-test-tool".into(),
+            action: Action::Upgrade,
+            old_version: Some("1.0-2".into()),
+            new_version: Some("1.0-3".into()),
+        };
+        let findings = find_cves(&[change], None);
+        assert_eq!(findings[0].cves, ["CVE-2026-1234"]);
+        assert!(findings[0].changelog);
+        assert_eq!(
+            fake.calls(),
+            ["apt-get -qq changelog rhino-test-tool=1.0-3"]
+        );
+    }
+
+    #[test]
+    fn feed_matches_fixes_between_the_versions() {
+        let feed = parse_json(
+            r#"{
+              "USN-6000-1": {"cves": ["CVE-2024-0001", "https://launchpad.net/bugs/1"],
+                             "releases": {"noble": {"binaries": {"openssl": {"version": "3.0.13-0ubuntu3.2"}}}}},
+              "USN-6001-1": {"cves": ["CVE-2024-0002"],
+                             "releases": {"noble": {"sources": {"openssl": {"version": "3.0.13-0ubuntu3.5"}}}}},
+              "USN-5000-1": {"cves": ["CVE-2023-0003"],
+                             "releases": {"noble": {"binaries": {"openssl": {"version": "3.0.13-0ubuntu3"}}}}}
+            }"#,
+        )
+        .unwrap();
+        let matches = feed_matches(
+            &feed,
+            "openssl:amd64",
+            "3.0.13-0ubuntu3",
+            "3.0.13-0ubuntu3.2",
+        );
+        assert_eq!(
+            matches,
+            [("USN-6000-1".to_string(), vec!["CVE-2024-0001".to_string()])]
+        );
+        assert!(feed_matches(&feed, "libssl3", "1", "9").is_empty());
+    }
+}
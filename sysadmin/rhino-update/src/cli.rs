@@ -0,0 +1,458 @@
+use std::env;
+use std::iter;
+use std::sync::atomic::Ordering;
+
+use crate::backends::{all_backends, PackageBackend, BACKEND_NAMES};
+use crate::changelog::cmd_cves;
+use crate::config::{load_config, prescan_option};
+use crate::exec::{parse_duration, RETRY_DELAY_SECS, STEP_RETRIES, STEP_TIMEOUT_SECS};
+use crate::history::{cmd_history, cmd_show};
+use crate::holds::{cmd_hold, parse_hold, HoldAction};
+use crate::plan::cmd_plan;
+use crate::reboot::{parse_reboot_time, RebootPolicy};
+use crate::rollback::cmd_rollback;
+use crate::schedule::{cmd_schedule, ScheduleAction};
+use crate::snapshot::SnapshotMode;
+use crate::ui::{init_styling, ColorMode, RED, UI_TO_STDERR};
+use crate::update::cmd_update;
+use crate::window::{parse_timezone, parse_window, TimeZone, Window};
+
+// Command-line options --------------------------------------------------------
+pub const USAGE: &str = "\
+Usage: rhino-update [OPTIONS] [plan]
+       rhino-update rollback [RUN_ID] [--dry-run] [--restore-snapshot]
+       rhino-update history [--limit N] [--since DATE]
+       rhino-update show [RUN_ID] [--report json]
+       rhino-update schedule install|status|remove
+       rhino-update hold add|remove|list [[BACKEND:]PACKAGE...]
+       rhino-update cves [RUN_ID|last|pending] [--cve-feed PATH] [--report json]
+
+Commands:
+  plan                    Same as --dry-run
+  rollback [RUN_ID]       Restore the package versions from before RUN_ID
+                          (default: the latest update); what cannot be
+                          reverted is reported, or with --restore-snapshot
+                          (or when confirmed) the run's snapshot is restored
+  history                 List recorded runs, newest first (default limit 20;
+                          --since takes a date/time prefix such as 2026-10-13)
+  show [RUN_ID]           Show every step and package change of a run
+                          (default: the latest run)
+  schedule install        Install and enable a systemd timer for unattended runs
+                          (--calendar, default \"Sun 03:00\"; --randomized-delay,
+                          default 1h; --config/--profile are passed through)
+  schedule status         Show the timer and the last unattended run
+  schedule remove         Disable and delete the timer and service
+  hold add PKG...         Keep packages at their installed version; prefix with
+                          flatpak:, snap: or pacstall: (default apt), e.g.
+                          nvidia-driver-535 flatpak:org.mozilla.firefox; a
+                          pacstall hold pauses all pacstall upgrades, as
+                          pacstall cannot upgrade around a package
+  hold remove PKG...      Release holds again
+  hold list               Show held packages per backend
+  cves [RUN_ID]           List the CVEs fixed by pending apt upgrades, or by
+                          RUN_ID (\"last\" for the latest update), from apt
+                          changelogs and an optional --cve-feed USN JSON file
+
+Options:
+  -n, --dry-run           Show what would be upgraded, installed and removed, then exit
+      --config PATH       Read settings from PATH (default /etc/rhino-update/config.toml)
+      --profile NAME      Apply the [profile.NAME] section of the config
+      --backend LIST      Only use these backends (apt,pacstall,flatpak,snap)
+      --security-only     Only install upgrades from a security origin (apt
+                          *-security pockets); other backends are skipped
+      --changelogs        Show the changelog entries each apt upgrade brings in
+                          (also recorded in the report; works with plan and show)
+      --no-cleanup        Skip purging orphaned packages
+      --hold LIST         Hold these [BACKEND:]PACKAGEs before updating
+      --snapshot[=MODE]   Take a pre-update snapshot (auto, timeshift or btrfs)
+      --no-snapshot       Do not take a snapshot (overrides the config)
+      --restore-snapshot  Let rollback restore the whole system from the run's
+                          snapshot when packages cannot be reverted one by one
+      --skip-preflight    Do not check disk space, locks, power and /boot first
+                          (failed checks otherwise abort with exit code 3)
+      --pre-hook CMD      Run CMD with sh -c before updating; failure aborts
+      --post-hook CMD     Run CMD with sh -c after updating
+      --restart-services  Restart services still using replaced libraries
+                          (session-critical units are only reported)
+      --reboot POLICY     never (default), if-needed, or at HH:MM — reboot
+                          via shutdown(8) when the upgrade requires it
+      --report json       Emit a machine-readable run report (stdout unless --report-file)
+      --report-file PATH  Write the report to PATH (implies --report json)
+      --window SPEC       Only update inside this window, e.g. \"Mon-Fri 22:00-06:00\"
+                          (repeatable; outside it the run exits 75, deferred)
+      --timezone TZ       Zone for --window: local (default), UTC, +HH:MM or a name
+      --blackout DATES    Never update on these days (YYYY-MM-DD, comma-separated)
+      --ignore-windows    Run now regardless of maintenance windows
+      --timeout SPAN      Stop any step running longer than SPAN (default 2h; 0 = never)
+      --retries N         Retry transient failures (lock held, mirror down) N times
+                          (default 2)
+      --retry-delay SPAN  Wait before the first retry, doubling each time (default 30s)
+      --log-file PATH     Log runs to PATH (default /var/log/rhino-update.log,
+                          rotated at 5 MiB); records also go to journald
+      --color[=WHEN]      auto (default), always or never; auto honours NO_COLOR,
+                          CLICOLOR_FORCE, TERM=dumb and whether output is a TTY
+      --no-color          Same as --color=never
+      --non-interactive   Plain, uncoloured output for timers and logs
+      --no-elevate        Fail instead of re-running through sudo or pkexec
+                          when not root (non-interactive runs only try sudo -n)
+  -h, --help              Show this help
+
+Ctrl-C, SIGTERM and SIGHUP are passed to the running step, but a working dpkg
+is left to finish unless the signal is repeated. No further steps start, dpkg
+is repaired with `dpkg --configure -a` if needed, and the run exits 128+signal
+(130 for Ctrl-C, 143 for SIGTERM) recorded as \"interrupted\".";
+
+#[derive(Debug, Default, PartialEq, Eq)]
+pub enum Subcommand {
+    #[default]
+    Update,
+    Rollback {
+        run_id: Option<String>,
+    },
+    History,
+    Show {
+        run_id: Option<String>,
+    },
+    Schedule {
+        action: Option<ScheduleAction>,
+    },
+    Hold {
+        action: Option<HoldAction>,
+        packages: Vec<String>,
+    },
+    Cves {
+        run_id: Option<String>,
+    },
+}
+
+#[derive(Debug, Default)]
+pub struct Options {
+    pub command: Subcommand,
+    pub dry_run: bool,
+    pub report: bool,
+    pub report_file: Option<String>,
+    pub backends: Vec<String>,
+    pub snapshot: SnapshotMode,
+    pub limit: Option<usize>,
+    pub since: Option<String>,
+    pub skip_preflight: bool,
+    pub restart_services: bool,
+    pub reboot: RebootPolicy,
+    pub no_cleanup: bool,
+    pub holds: Vec<String>,
+    pub pre_hooks: Vec<String>,
+    pub post_hooks: Vec<String>,
+    pub config: Option<String>,
+    pub profile: Option<String>,
+    pub calendar: Option<String>,
+    pub randomized_delay: Option<String>,
+    pub non_interactive: bool,
+    pub no_elevate: bool,
+    pub restore_snapshot: bool,
+    // The command line to re-run through sudo or pkexec when not root, the
+    // program first; None never elevates.
+    pub elevate_argv: Option<Vec<String>>,
+    pub security_only: bool,
+    pub cve_feed: Option<String>,
+    pub changelogs: bool,
+    pub log_file: Option<String>,
+    pub color: Option<ColorMode>,
+    pub timeout: Option<u64>,
+    pub retries: Option<u32>,
+    pub retry_delay: Option<u64>,
+    pub windows: Vec<Window>,
+    pub timezone: TimeZone,
+    pub blackouts: Vec<String>,
+    pub ignore_windows: bool,
+    pub help: bool,
+}
+
+// Later arguments override earlier ones, so config settings are parsed first.
+pub fn parse_args<I: Iterator<Item = String>>(opts: &mut Options, args: I) -> Result<(), String> {
+    let mut args = args.peekable();
+    while let Some(arg) = args.next() {
+        let (flag, inline) = match arg.split_once('=') {
+            Some((flag, value)) if flag.starts_with("--") => {
+                (flag.to_string(), Some(value.to_string()))
+            }
+            _ => (arg.clone(), None),
+        };
+        let mut value = |name: &str| {
+            inline
+                .clone()
+                .or_else(|| args.next())
+                .ok_or_else(|| format!("{name} requires a value"))
+        };
+        match flag.as_str() {
+            "-n" | "--dry-run" => opts.dry_run = true,
+            "plan" if opts.command == Subcommand::Update => opts.dry_run = true,
+            "rollback" if opts.command == Subcommand::Update => {
+                opts.command = Subcommand::Rollback { run_id: None }
+            }
+            "history" if opts.command == Subcommand::Update => opts.command = Subcommand::History,
+            "show" if opts.command == Subcommand::Update => {
+                opts.command = Subcommand::Show { run_id: None }
+            }
+            "cves" if opts.command == Subcommand::Update => {
+                opts.command = Subcommand::Cves { run_id: None }
+            }
+            "--cve-feed" => opts.cve_feed = Some(value("--cve-feed")?),
+            "--changelogs" => opts.changelogs = true,
+            "--log-file" => opts.log_file = Some(value("--log-file")?),
+            "--timeout" => opts.timeout = Some(parse_duration(&value("--timeout")?)?),
+            "--retry-delay" => opts.retry_delay = Some(parse_duration(&value("--retry-delay")?)?),
+            "--retries" => {
+                opts.retries = Some(
+                    value("--retries")?
+                        .parse()
+                        .map_err(|_| "--retries expects a number".to_string())?,
+                )
+            }
+            "--no-changelogs" => opts.changelogs = false,
+            "hold" if opts.command == Subcommand::Update => {
+                opts.command = Subcommand::Hold {
+                    action: None,
+                    packages: Vec::new(),
+                }
+            }
+            "schedule" if opts.command == Subcommand::Update => {
+                opts.command = Subcommand::Schedule { action: None }
+            }
+            "--calendar" => opts.calendar = Some(value("--calendar")?),
+            "--randomized-delay" => opts.randomized_delay = Some(value("--randomized-delay")?),
+            "--non-interactive" => opts.non_interactive = true,
+            "--no-elevate" => opts.no_elevate = true,
+            "--elevate" => opts.no_elevate = false,
+            "--restore-snapshot" => opts.restore_snapshot = true,
+            "--no-restore-snapshot" => opts.restore_snapshot = false,
+            "--no-color" => opts.color = Some(ColorMode::Never),
+            "--limit" => {
+                opts.limit = Some(
+                    value("--limit")?
+                        .parse()
+                        .map_err(|_| "--limit expects a number".to_string())?,
+                )
+            }
+            "--since" => opts.since = Some(value("--since")?),
+            // Already applied while loading the config; kept for `schedule`.
+            "--config" => opts.config = Some(value("--config")?),
+            "--profile" => opts.profile = Some(value("--profile")?),
+            "--skip-preflight" | "--no-preflight" => opts.skip_preflight = true,
+            "--preflight" => opts.skip_preflight = false,
+            "--restart-services" => opts.restart_services = true,
+            "--no-restart-services" => opts.restart_services = false,
+            "--cleanup" => opts.no_cleanup = false,
+            "--no-cleanup" => opts.no_cleanup = true,
+            "--hold" => {
+                opts.holds = value("--hold")?
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|p| !p.is_empty())
+                    .map(|p| parse_hold(p).map(|_| p.to_string()))
+                    .collect::<Result<_, _>>()?
+            }
+            "--pre-hook" => opts.pre_hooks.push(value("--pre-hook")?),
+            "--post-hook" => opts.post_hooks.push(value("--post-hook")?),
+            "--no-snapshot" => opts.snapshot = SnapshotMode::Off,
+            "--window" => opts.windows.push(parse_window(&value("--window")?)?),
+            "--timezone" => opts.timezone = parse_timezone(&value("--timezone")?)?,
+            "--blackout" => {
+                opts.blackouts = value("--blackout")?
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|d| !d.is_empty())
+                    .map(|d| {
+                        let valid = d.len() == 10
+                            && d.split('-').map(str::len).eq([4, 2, 2])
+                            && d.split('-').all(|p| p.parse::<u32>().is_ok());
+                        valid.then(|| d.to_string()).ok_or_else(|| {
+                            format!("Invalid blackout date: {d} (expected YYYY-MM-DD)")
+                        })
+                    })
+                    .collect::<Result<_, _>>()?
+            }
+            "--ignore-windows" => opts.ignore_windows = true,
+            "--security-only" => opts.security_only = true,
+            "--no-security-only" => opts.security_only = false,
+            "--reboot" => {
+                let policy = value("--reboot")?;
+                opts.reboot = match policy.as_str() {
+                    "never" => RebootPolicy::Never,
+                    "if-needed" => RebootPolicy::IfNeeded,
+                    // Accept "at HH:MM" as one argument or two.
+                    "at" => RebootPolicy::At(parse_reboot_time(
+                        &args.next().ok_or("--reboot=at requires a time")?,
+                    )?),
+                    other => match other.strip_prefix("at ") {
+                        Some(time) => RebootPolicy::At(parse_reboot_time(time.trim())?),
+                        None => return Err(format!("Unknown reboot policy: {other}")),
+                    },
+                }
+            }
+            "--report" => match value("--report")?.as_str() {
+                "json" => opts.report = true,
+                other => return Err(format!("Unsupported report format: {other}")),
+            },
+            "--backend" => {
+                opts.backends.clear();
+                for name in value("--backend")?.split(',') {
+                    if !BACKEND_NAMES.contains(&name) {
+                        return Err(format!(
+                            "Unknown backend: {name} (expected one of {})",
+                            BACKEND_NAMES.join(", ")
+                        ));
+                    }
+                    opts.backends.push(name.to_string());
+                }
+            }
+            "--snapshot" => {
+                opts.snapshot = match inline.as_deref() {
+                    None | Some("auto") => SnapshotMode::Auto,
+                    Some("off") => SnapshotMode::Off,
+                    Some("timeshift") => SnapshotMode::Timeshift,
+                    Some("btrfs") => SnapshotMode::Btrfs,
+                    Some(other) => return Err(format!("Unknown snapshot mode: {other}")),
+                }
+            }
+            "--color" | "--colour" => {
+                opts.color = Some(match inline.as_deref() {
+                    None | Some("always") => ColorMode::Always,
+                    Some("auto") => ColorMode::Auto,
+                    Some("never") => ColorMode::Never,
+                    Some(other) => return Err(format!("Unknown color mode: {other}")),
+                })
+            }
+            "--report-file" => {
+                opts.report = true;
+                opts.report_file = Some(value("--report-file")?);
+            }
+            "-h" | "--help" => opts.help = true,
+            other => match &mut opts.command {
+                Subcommand::Rollback { run_id: id @ None }
+                | Subcommand::Show { run_id: id @ None }
+                | Subcommand::Cves { run_id: id @ None }
+                    if !other.starts_with('-') =>
+                {
+                    *id = Some(other.to_string())
+                }
+                Subcommand::Schedule {
+                    action: action @ None,
+                } => {
+                    *action = Some(match other {
+                        "install" => ScheduleAction::Install,
+                        "status" => ScheduleAction::Status,
+                        "remove" => ScheduleAction::Remove,
+                        _ => return Err(format!("Unknown schedule action: {other}")),
+                    })
+                }
+                Subcommand::Hold {
+                    action: action @ None,
+                    ..
+                } => {
+                    *action = Some(match other {
+                        "add" => HoldAction::Add,
+                        "remove" => HoldAction::Remove,
+                        "list" => HoldAction::List,
+                        _ => return Err(format!("Unknown hold action: {other}")),
+                    })
+                }
+                Subcommand::Hold {
+                    action: Some(HoldAction::Add | HoldAction::Remove),
+                    packages,
+                } if !other.starts_with('-') => {
+                    parse_hold(other)?;
+                    packages.push(other.to_string())
+                }
+                _ => return Err(format!("Unknown argument: {other}")),
+            },
+        }
+    }
+    Ok(())
+}
+
+// Entry point -----------------------------------------------------------------
+// Apply the process-wide settings in `opts`: output styling, where the
+// human-readable output goes, and step timeouts and retries.
+pub fn configure(opts: &Options) {
+    UI_TO_STDERR.store(opts.report && opts.report_file.is_none(), Ordering::Relaxed);
+    if let Some(timeout) = opts.timeout {
+        STEP_TIMEOUT_SECS.store(timeout, Ordering::Relaxed);
+    }
+    if let Some(retries) = opts.retries {
+        STEP_RETRIES.store(retries, Ordering::Relaxed);
+    }
+    if let Some(delay) = opts.retry_delay {
+        RETRY_DELAY_SECS.store(delay, Ordering::Relaxed);
+    }
+    init_styling(match opts.color {
+        Some(mode) => mode,
+        None if opts.non_interactive => ColorMode::Never,
+        None => ColorMode::Auto,
+    });
+}
+
+// Options for command-line `args` (without the program name) on top of the
+// config file and profile they select.
+pub fn load_options(args: Vec<String>) -> Result<Options, String> {
+    let config = load_config(
+        prescan_option(&args, "--config").as_deref(),
+        prescan_option(&args, "--profile").as_deref(),
+    )?;
+    let mut opts = Options::default();
+    parse_args(&mut opts, config.into_iter()).map_err(|e| format!("config: {e}"))?;
+    parse_args(&mut opts, args.into_iter())?;
+    Ok(opts)
+}
+
+// The rhino-update command: `args` exclude the program name. Returns the
+// exit code.
+pub fn run(args: Vec<String>) -> i32 {
+    init_styling(ColorMode::Auto);
+    let program = env::current_exe().map(|exe| exe.display().to_string());
+    let elevate_argv = program
+        .ok()
+        .map(|p| iter::once(p).chain(args.clone()).collect());
+    let mut opts = match load_options(args) {
+        Ok(opts) => opts,
+        Err(msg) => {
+            color_print!(RED, "❌ {}\n", msg);
+            eprintln!("{USAGE}");
+            return 2;
+        }
+    };
+    if opts.help {
+        println!("{USAGE}");
+        return 0;
+    }
+    opts.elevate_argv = elevate_argv;
+    configure(&opts);
+
+    let backends: Vec<Box<dyn PackageBackend>> = all_backends()
+        .into_iter()
+        .filter(|b| opts.backends.is_empty() || opts.backends.iter().any(|n| n == b.name()))
+        .collect();
+
+    match &opts.command {
+        Subcommand::Update if opts.dry_run => cmd_plan(&opts, &backends).exit_code(),
+        Subcommand::Update => cmd_update(&opts, &backends).exit_code(),
+        Subcommand::Rollback { run_id } => cmd_rollback(&opts, run_id.as_deref()).exit_code(),
+        Subcommand::History => cmd_history(&opts),
+        Subcommand::Show { run_id } => cmd_show(&opts, run_id.as_deref()),
+        Subcommand::Schedule {
+            action: Some(action),
+        } => cmd_schedule(&opts, *action),
+        Subcommand::Schedule { action: None } => {
+            color_print!(RED, "❌ schedule requires install, status or remove\n");
+            2
+        }
+        Subcommand::Hold {
+            action: Some(action),
+            packages,
+        } => cmd_hold(&opts, &backends, *action, packages),
+        Subcommand::Cves { run_id } => cmd_cves(&opts, &backends, run_id.as_deref()),
+        Subcommand::Hold { action: None, .. } => {
+            color_print!(RED, "❌ hold requires add, remove or list\n");
+            2
+        }
+    }
+}
@@ -0,0 +1,327 @@
+use std::fs;
+use std::io;
+
+// Configuration file ----------------------------------------------------------
+// A TOML subset: [tables], key = value with strings, booleans, integers and
+// (multi-line) arrays. Each setting maps to the long option of the same name,
+// so the config is validated exactly like the command line and command-line
+// flags override it. For example:
+//
+//   backends = ["apt", "flatpak"]
+//   snapshot = "auto"
+//   holds = ["linux-image-generic"]
+//
+//   [hooks]
+//   post_update = ["systemctl start backup.service"]
+//
+//   [profile.server]
+//   backends = ["apt"]
+//   reboot = "at 03:30"
+//   restart_services = true
+//
+//   [maintenance]
+//   windows = ["Mon-Fri 22:00-06:00", "Sat,Sun 00:00-24:00"]
+//   timezone = "Europe/Berlin"
+//   blackout = ["2026-12-24", "2026-12-31"]
+pub const DEFAULT_CONFIG: &str = "/etc/rhino-update/config.toml";
+
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum TomlValue {
+    Str(String),
+    Bool(bool),
+    Int(i64),
+    Arr(Vec<TomlValue>),
+}
+
+// Flattened settings: ("profile.server.hooks.pre_update", value).
+pub(crate) fn parse_toml(text: &str) -> Result<Vec<(String, TomlValue)>, String> {
+    let mut settings = Vec::new();
+    let mut table = String::new();
+    let mut lines = text.lines().enumerate();
+    while let Some((n, raw)) = lines.next() {
+        let mut line = strip_toml_comment(raw).trim().to_string();
+        if line.is_empty() {
+            continue;
+        }
+        if let Some(header) = line.strip_prefix('[') {
+            let name = header
+                .strip_suffix(']')
+                .ok_or_else(|| format!("line {}: unterminated table header", n + 1))?;
+            table = name.split('.').map(str::trim).collect::<Vec<_>>().join(".");
+            continue;
+        }
+        // Arrays may span lines; keep reading until the brackets balance.
+        while bracket_depth(&line) > 0 {
+            let (_, next) = lines
+                .next()
+                .ok_or_else(|| format!("line {}: unterminated array", n + 1))?;
+            line.push(' ');
+            line.push_str(strip_toml_comment(next).trim());
+        }
+        let (key, value) = line
+            .split_once('=')
+            .ok_or_else(|| format!("line {}: expected key = value", n + 1))?;
+        let key = key.trim().trim_matches('"');
+        let (value, rest) =
+            parse_toml_value(value.trim()).map_err(|e| format!("line {}: {e}", n + 1))?;
+        if !rest.trim().is_empty() {
+            return Err(format!("line {}: unexpected '{}'", n + 1, rest.trim()));
+        }
+        let path = if table.is_empty() {
+            key.to_string()
+        } else {
+            format!("{table}.{key}")
+        };
+        settings.push((path, value));
+    }
+    Ok(settings)
+}
+
+pub(crate) fn strip_toml_comment(line: &str) -> &str {
+    let mut quote = None;
+    let mut escaped = false;
+    for (i, c) in line.char_indices() {
+        match (quote, c) {
+            (Some('"'), '\\') if !escaped => {
+                escaped = true;
+                continue;
+            }
+            (Some(q), c) if c == q && !escaped => quote = None,
+            (None, '"' | '\'') => quote = Some(c),
+            (None, '#') => return &line[..i],
+            _ => {}
+        }
+        escaped = false;
+    }
+    line
+}
+
+pub(crate) fn bracket_depth(line: &str) -> i32 {
+    let value = line.split_once('=').map_or("", |(_, v)| v);
+    let mut depth = 0;
+    let mut quote = None;
+    for c in value.chars() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (None, '"' | '\'') => quote = Some(c),
+            (None, '[') => depth += 1,
+            (None, ']') => depth -= 1,
+            _ => {}
+        }
+    }
+    depth
+}
+
+// Parse one value from the front of `s`, returning it and the remainder.
+pub(crate) fn parse_toml_value(s: &str) -> Result<(TomlValue, &str), String> {
+    if let Some(rest) = s.strip_prefix('"') {
+        let mut out = String::new();
+        let mut chars = rest.char_indices();
+        while let Some((i, c)) = chars.next() {
+            match c {
+                '"' => return Ok((TomlValue::Str(out), &rest[i + 1..])),
+                '\\' => match chars.next().map(|(_, e)| e) {
+                    Some('n') => out.push('\n'),
+                    Some('t') => out.push('\t'),
+                    Some(e @ ('"' | '\\')) => out.push(e),
+                    _ => return Err("unsupported escape in string".into()),
+                },
+                c => out.push(c),
+            }
+        }
+        Err("unterminated string".into())
+    } else if let Some(rest) = s.strip_prefix('\'') {
+        let end = rest.find('\'').ok_or("unterminated string")?;
+        Ok((TomlValue::Str(rest[..end].to_string()), &rest[end + 1..]))
+    } else if let Some(mut rest) = s.strip_prefix('[') {
+        let mut items = Vec::new();
+        loop {
+            rest = rest.trim_start();
+            if let Some(after) = rest.strip_prefix(']') {
+                return Ok((TomlValue::Arr(items), after));
+            }
+            let (item, after) = parse_toml_value(rest)?;
+            items.push(item);
+            rest = after.trim_start();
+            rest = rest.strip_prefix(',').unwrap_or(rest);
+        }
+    } else {
+        let end = s
+            .find(|c: char| c == ',' || c == ']' || c.is_whitespace())
+            .unwrap_or(s.len());
+        let value = match &s[..end] {
+            "true" => TomlValue::Bool(true),
+            "false" => TomlValue::Bool(false),
+            word => TomlValue::Int(
+                word.replace('_', "")
+                    .parse()
+                    .map_err(|_| format!("invalid value '{word}'"))?,
+            ),
+        };
+        Ok((value, &s[end..]))
+    }
+}
+
+// The long option a config key stands for.
+pub(crate) fn config_flag(key: &str) -> String {
+    match key {
+        "backends" => "backend".into(),
+        "holds" => "hold".into(),
+        "hooks.pre_update" => "pre-hook".into(),
+        "hooks.post_update" => "post-hook".into(),
+        "maintenance.windows" => "window".into(),
+        "maintenance.timezone" => "timezone".into(),
+        "maintenance.blackout" => "blackout".into(),
+        other => other.replace('_', "-"),
+    }
+}
+
+pub(crate) fn config_args(key: &str, value: &TomlValue) -> Result<Vec<String>, String> {
+    let flag = config_flag(key);
+    let scalar = |v: &TomlValue| match v {
+        TomlValue::Str(s) => Ok(s.clone()),
+        TomlValue::Int(n) => Ok(n.to_string()),
+        _ => Err(format!("{key}: expected a string or number")),
+    };
+    Ok(match value {
+        TomlValue::Bool(true) => vec![format!("--{flag}")],
+        TomlValue::Bool(false) => vec![format!("--no-{flag}")],
+        // Hooks and windows repeat; other lists are comma-joined.
+        TomlValue::Arr(items) if key.starts_with("hooks.") || key == "maintenance.windows" => items
+            .iter()
+            .map(|item| scalar(item).map(|cmd| format!("--{flag}={cmd}")))
+            .collect::<Result<_, _>>()?,
+        TomlValue::Arr(items) => {
+            let items: Vec<String> = items.iter().map(scalar).collect::<Result<_, _>>()?;
+            vec![format!("--{flag}={}", items.join(","))]
+        }
+        other => vec![format!("--{flag}={}", scalar(other)?)],
+    })
+}
+
+// The `--config` and `--profile` values, read ahead of full option parsing.
+pub fn prescan_option(args: &[String], name: &str) -> Option<String> {
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        if arg == name {
+            return iter.next().cloned();
+        }
+        if let Some(value) = arg.strip_prefix(name).and_then(|v| v.strip_prefix('=')) {
+            return Some(value.to_string());
+        }
+    }
+    None
+}
+
+// Top-level settings followed by the selected profile's, as option arguments.
+pub fn load_config(path: Option<&str>, profile: Option<&str>) -> Result<Vec<String>, String> {
+    let file = path.unwrap_or(DEFAULT_CONFIG);
+    let text = match fs::read_to_string(file) {
+        Ok(text) => text,
+        // The default config is optional; an explicit --config is not.
+        Err(e) if path.is_none() && e.kind() == io::ErrorKind::NotFound => {
+            return match profile {
+                Some(name) => Err(format!(
+                    "profile '{name}' requested but {file} does not exist"
+                )),
+                None => Ok(Vec::new()),
+            };
+        }
+        Err(e) => return Err(format!("{file}: {e}")),
+    };
+    let settings = parse_toml(&text).map_err(|e| format!("{file}: {e}"))?;
+    let mut args = Vec::new();
+    for (key, value) in settings.iter().filter(|(k, _)| !k.starts_with("profile.")) {
+        args.extend(config_args(key, value).map_err(|e| format!("{file}: {e}"))?);
+    }
+    if let Some(name) = profile {
+        let prefix = format!("profile.{name}.");
+        let mut found = false;
+        for (key, value) in &settings {
+            if let Some(key) = key.strip_prefix(&prefix) {
+                found = true;
+                args.extend(config_args(key, value).map_err(|e| format!("{file}: {e}"))?);
+            }
+        }
+        if !found {
+            return Err(format!("{file}: no [profile.{name}] section"));
+        }
+    }
+    Ok(args)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn toml_tables_and_arrays_flatten_to_settings() {
+        let text = r#"
+# Defaults for every host.
+backends = ["apt",   # the system
+            "flatpak"]
+snapshot = "auto"  # or "never"
+retries = 3
+
+[hooks]
+post_update = ["echo '#1' done"]
+
+[profile.server]
+restart_services = true
+"#;
+        let settings = parse_toml(text).unwrap();
+        let strs = |items: &[&str]| {
+            TomlValue::Arr(
+                items
+                    .iter()
+                    .map(|s| TomlValue::Str(s.to_string()))
+                    .collect(),
+            )
+        };
+        assert_eq!(
+            settings,
+            [
+                ("backends".to_string(), strs(&["apt", "flatpak"])),
+                ("snapshot".to_string(), TomlValue::Str("auto".into())),
+                ("retries".to_string(), TomlValue::Int(3)),
+                ("hooks.post_update".to_string(), strs(&["echo '#1' done"])),
+                (
+                    "profile.server.restart_services".to_string(),
+                    TomlValue::Bool(true)
+                ),
+            ]
+        );
+        assert_eq!(
+            config_args("hooks.post_update", &settings[3].1).unwrap(),
+            ["--post-hook=echo '#1' done"]
+        );
+        assert_eq!(
+            config_args("backends", &settings[0].1).unwrap(),
+            ["--backend=apt,flatpak"]
+        );
+        assert_eq!(
+            config_args("restart_services", &TomlValue::Bool(false)).unwrap(),
+            ["--no-restart-services"]
+        );
+    }
+
+    #[test]
+    fn toml_errors_name_the_line() {
+        assert_eq!(
+            parse_toml("snapshot = \"auto\"\n[hooks\n"),
+            Err("line 2: unterminated table header".into())
+        );
+        assert_eq!(
+            parse_toml("backends = [\"apt\",\n"),
+            Err("line 1: unterminated array".into())
+        );
+        assert_eq!(
+            parse_toml("reboot = soon"),
+            Err("line 1: invalid value 'soon'".into())
+        );
+        assert_eq!(
+            parse_toml("holds"),
+            Err("line 1: expected key = value".into())
+        );
+    }
+}
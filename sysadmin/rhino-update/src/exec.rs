@@ -0,0 +1,708 @@
+use std::cell::RefCell;
+use std::env;
+use std::ffi::OsString;
+use std::io::{self, Read, Write};
+use std::mem;
+use std::os::unix::process::CommandExt;
+use std::process::{Child, Command, ExitStatus, Output, Stdio};
+use std::rc::Rc;
+use std::sync::atomic::{AtomicU32, AtomicU64, Ordering};
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime};
+
+use crate::backends::{argv, diff_inventory, inventory, Action, PackageBackend, PackageChange};
+use crate::history::format_secs;
+use crate::log::{log_step, log_step_start};
+use crate::privilege::is_root;
+use crate::signals::{
+    dpkg_running_in, forward_signal, interrupted, signal_group, signal_name, RECOVERING, SIGKILL,
+    SIGNAL_COUNT, SIGTERM,
+};
+use crate::ui::{BLUE, BOLD, CYAN, RED, UI_TO_STDERR, YELLOW};
+
+// Run a command, streaming its output, and record how it went ----------------
+#[derive(Debug)]
+pub struct Step {
+    pub backend: Option<&'static str>,
+    pub argv: Vec<String>,
+    pub description: String,
+    pub started_at: SystemTime,
+    pub finished_at: SystemTime,
+    pub duration: Duration,
+    pub exit_code: Option<i32>,
+    pub error: Option<String>,
+    pub timed_out: bool,
+    pub attempts: u32,
+    // The last STDERR_TAIL_BYTES of stderr, for classify_failure.
+    pub stderr_tail: String,
+    // Everything printed on stdout, over all attempts.
+    pub output: String,
+    // What the inventory diff says changed; rollback relies on it.
+    pub packages: Vec<PackageChange>,
+    // What the backend's own output says it did (see step_transactions).
+    pub transactions: Vec<PackageChange>,
+}
+
+impl Step {
+    pub fn new(
+        cmd: &[String],
+        description: &str,
+        started_at: SystemTime,
+        duration: Duration,
+    ) -> Self {
+        Step {
+            backend: None,
+            argv: cmd.to_vec(),
+            description: description.to_string(),
+            started_at,
+            finished_at: SystemTime::now(),
+            duration,
+            exit_code: None,
+            error: None,
+            timed_out: false,
+            attempts: 1,
+            stderr_tail: String::new(),
+            output: String::new(),
+            packages: Vec::new(),
+            transactions: Vec::new(),
+        }
+    }
+
+    pub fn succeeded(&self) -> bool {
+        self.error.is_none() && self.exit_code == Some(0)
+    }
+
+    // Exit status to hand back to the caller when this step failed.
+    pub fn failure_code(&self) -> i32 {
+        match (&self.error, self.exit_code) {
+            _ if self.timed_out => EXIT_TIMEOUT,
+            (Some(_), _) => 127,
+            (None, Some(code)) if code != 0 => code,
+            _ => 1,
+        }
+    }
+}
+
+// Command runner --------------------------------------------------------------
+// Every external command goes through the current thread's CommandRunner, so
+// the update flow can run against a scripted fake or a PATH of stub programs.
+pub trait CommandRunner {
+    // A step in the C locale: stdout and stderr stream to the terminal while
+    // being captured.
+    fn run(&self, cmd: &[String]) -> io::Result<StepOutput>;
+    // A read-only query in the C locale; stderr is discarded.
+    fn output(&self, cmd: &[String]) -> io::Result<Output>;
+    fn exists(&self, program: &str) -> bool;
+    // Whether the commands run as root, which changing packages needs. A
+    // runner standing in for the system may claim so.
+    fn is_root(&self) -> bool {
+        is_root()
+    }
+}
+
+// How a step ended and what it printed.
+pub struct StepOutput {
+    pub status: ExitStatus,
+    pub timed_out: bool,
+    pub stdout: String,
+    // The last STDERR_TAIL_BYTES of stderr.
+    pub stderr_tail: String,
+}
+
+// Runs the real programs, looked up on `path` instead of $PATH when set.
+#[derive(Default)]
+pub struct SystemRunner {
+    pub path: Option<OsString>,
+}
+
+impl SystemRunner {
+    pub fn command(&self, cmd: &[String]) -> Command {
+        let mut command = Command::new(cmd.first().map_or("", String::as_str));
+        command.args(&cmd[cmd.len().min(1)..]).stdin(Stdio::null());
+        if let Some(path) = &self.path {
+            command.env("PATH", path);
+        }
+        command
+    }
+}
+
+impl CommandRunner for SystemRunner {
+    fn run(&self, cmd: &[String]) -> io::Result<StepOutput> {
+        // A process group of its own, so a timeout can stop everything it
+        // spawned. The C locale keeps the transaction parsers and
+        // classify_failure working whatever the user's language; LC_ALL
+        // because it would override LC_MESSAGES.
+        self.command(cmd)
+            .env("DEBIAN_FRONTEND", "noninteractive")
+            .env("LC_ALL", "C")
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .process_group(0)
+            .spawn()
+            .and_then(wait_child)
+    }
+
+    fn output(&self, cmd: &[String]) -> io::Result<Output> {
+        self.command(cmd)
+            .env("LC_ALL", "C")
+            .stderr(Stdio::null())
+            .output()
+    }
+
+    fn exists(&self, program: &str) -> bool {
+        self.path
+            .clone()
+            .or_else(|| env::var_os("PATH"))
+            .is_some_and(|paths| env::split_paths(&paths).any(|dir| dir.join(program).is_file()))
+    }
+}
+
+thread_local! {
+    static RUNNER: RefCell<Rc<dyn CommandRunner>> = RefCell::new(Rc::new(SystemRunner::default()));
+}
+
+pub fn runner() -> Rc<dyn CommandRunner> {
+    RUNNER.with(|current| Rc::clone(&current.borrow()))
+}
+
+// Install `runner` for this thread and return the one it replaces.
+pub fn set_runner(runner: Rc<dyn CommandRunner>) -> Rc<dyn CommandRunner> {
+    RUNNER.with(|current| current.replace(runner))
+}
+
+// Child output follows the human-readable output so a JSON report on stdout
+// stays parseable.
+pub(crate) fn child_stdout() -> Box<dyn Write + Send> {
+    if UI_TO_STDERR.load(Ordering::Relaxed) {
+        Box::new(io::stderr())
+    } else {
+        Box::new(io::stdout())
+    }
+}
+
+// When `tracked` is given, the installed packages of those backends are
+// compared before and after the command to record what it changed.
+pub fn run(cmd: &[String], description: &str, tracked: Option<&[Box<dyn PackageBackend>]>) -> Step {
+    if !description.is_empty() {
+        color_print!(format!("{BLUE}{BOLD}"), "➜ {}\n", description);
+    }
+    color_print!(CYAN, "▶ ");
+    color_print!("", "{}\n", cmd.join(" "));
+
+    if let Some(signum) = interrupted().filter(|_| !RECOVERING.load(Ordering::SeqCst)) {
+        let mut step = Step::new(cmd, description, SystemTime::now(), Duration::ZERO);
+        step.error = Some(format!(
+            "not started: interrupted by {}",
+            signal_name(signum)
+        ));
+        return step;
+    }
+    let number = log_step_start(cmd);
+    let before = tracked.map(inventory);
+    let started_at = SystemTime::now();
+    let clock = Instant::now();
+
+    let program = cmd.first().map(String::as_str).unwrap_or_default();
+    let result = runner().run(cmd);
+
+    let mut step = Step::new(cmd, description, started_at, clock.elapsed());
+    match result {
+        Ok(out) => {
+            step.exit_code = out.status.code();
+            step.timed_out = out.timed_out;
+            step.stderr_tail = out.stderr_tail;
+            step.output = out.stdout;
+            if let (None, Some(signum)) = (out.status.code(), interrupted()) {
+                step.error = Some(format!("interrupted by {}", signal_name(signum)));
+            }
+            if out.timed_out {
+                step.error = Some(format!(
+                    "timed out after {}",
+                    format_secs(STEP_TIMEOUT_SECS.load(Ordering::Relaxed) as f64)
+                ));
+            }
+        }
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {
+            step.error = Some(format!("command not found: {program}"))
+        }
+        Err(e) => step.error = Some(e.to_string()),
+    }
+    if let (Some(before), Some(backends)) = (before, tracked) {
+        step.packages = diff_inventory(&before, &inventory(backends));
+    }
+
+    if let Some(error) = &step.error {
+        color_print!(RED, "❌ {}\n", error);
+    } else if !step.succeeded() {
+        color_print!(
+            RED,
+            "❌ Command failed with exit code: {:?}\n",
+            step.exit_code
+        );
+    }
+    log_step(number, &step);
+    step
+}
+
+// Timeouts and retries --------------------------------------------------------
+// Every step is stopped after --timeout (SIGTERM to its process group, then
+// SIGKILL after KILL_GRACE) and exits with EXIT_TIMEOUT, though never while
+// dpkg is working in it; the caller then repairs dpkg. Package-manager steps
+// go through run_retrying, which repeats failures that look transient — a
+// lock held by another package manager, an unreachable or syncing mirror —
+// up to --retries times, doubling --retry-delay each time.
+pub(crate) const DEFAULT_TIMEOUT_SECS: u64 = 2 * 3600;
+pub(crate) const DEFAULT_RETRIES: u32 = 2;
+pub(crate) const DEFAULT_RETRY_DELAY_SECS: u64 = 30;
+pub(crate) const MAX_RETRY_DELAY_SECS: u64 = 600;
+pub(crate) const KILL_GRACE: Duration = Duration::from_secs(10);
+pub const EXIT_TIMEOUT: i32 = 124;
+pub(crate) const STDERR_TAIL_BYTES: usize = 16 * 1024;
+
+pub(crate) static STEP_TIMEOUT_SECS: AtomicU64 = AtomicU64::new(DEFAULT_TIMEOUT_SECS);
+pub(crate) static STEP_RETRIES: AtomicU32 = AtomicU32::new(DEFAULT_RETRIES);
+pub(crate) static RETRY_DELAY_SECS: AtomicU64 = AtomicU64::new(DEFAULT_RETRY_DELAY_SECS);
+
+// stderr messages that mean "try again later", and how to describe them.
+pub(crate) const TRANSIENT_ERRORS: [(&str, &str); 16] = [
+    ("Could not get lock", "package manager lock held"),
+    (
+        "Unable to acquire the dpkg frontend lock",
+        "package manager lock held",
+    ),
+    ("is another process using it", "package manager lock held"),
+    ("change in progress", "snap change in progress"),
+    ("Temporary failure resolving", "mirror unreachable"),
+    ("Could not resolve", "mirror unreachable"),
+    ("Error resolving", "mirror unreachable"),
+    ("Failed to fetch", "mirror unreachable"),
+    ("Connection timed out", "mirror unreachable"),
+    ("Connection refused", "mirror unreachable"),
+    ("Network is unreachable", "mirror unreachable"),
+    ("Unable to connect", "mirror unreachable"),
+    ("Could not connect", "mirror unreachable"),
+    ("Timeout was reached", "mirror unreachable"),
+    ("Hash Sum mismatch", "mirror is syncing"),
+    ("Service Unavailable", "mirror overloaded"),
+];
+
+// Copy `from` to `to` as it arrives, keeping the last `keep` bytes (all of
+// it when None) in the returned buffer; `done` is signalled at EOF.
+pub(crate) fn tee(
+    mut from: impl Read + Send + 'static,
+    mut to: Box<dyn Write + Send>,
+    keep: Option<usize>,
+    done: mpsc::Sender<()>,
+) -> Arc<Mutex<Vec<u8>>> {
+    let kept = Arc::new(Mutex::new(Vec::new()));
+    let buffer = Arc::clone(&kept);
+    thread::spawn(move || {
+        let mut buf = [0u8; 4096];
+        while let Ok(n @ 1..) = from.read(&mut buf) {
+            let _ = to.write_all(&buf[..n]);
+            let _ = to.flush();
+            let mut kept = buffer.lock().unwrap_or_else(|e| e.into_inner());
+            kept.extend_from_slice(&buf[..n]);
+            if let Some(keep) = keep {
+                let excess = kept.len().saturating_sub(keep);
+                kept.drain(..excess);
+            }
+        }
+        let _ = done.send(());
+    });
+    kept
+}
+
+// Stream the child's output through while capturing stdout for the backend
+// parsers and the tail of stderr for classify_failure, and stop the whole
+// process group past the timeout.
+pub(crate) fn wait_child(mut child: Child) -> io::Result<StepOutput> {
+    let (done_tx, done_rx) = mpsc::channel();
+    let mut streams = 0;
+    let stdout = child.stdout.take().map(|out| {
+        streams += 1;
+        tee(out, child_stdout(), None, done_tx.clone())
+    });
+    let tail = child.stderr.take().map(|err| {
+        streams += 1;
+        tee(
+            err,
+            Box::new(io::stderr()),
+            Some(STDERR_TAIL_BYTES),
+            done_tx,
+        )
+    });
+
+    let timeout = STEP_TIMEOUT_SECS.load(Ordering::Relaxed);
+    let deadline = (timeout > 0).then(|| Instant::now() + Duration::from_secs(timeout));
+    let mut timed_out = false;
+    let seen_at_spawn = SIGNAL_COUNT.load(Ordering::SeqCst);
+    let mut warned = false;
+    let status = loop {
+        if let Some(status) = child.try_wait()? {
+            break status;
+        }
+        if SIGNAL_COUNT.load(Ordering::SeqCst) != seen_at_spawn {
+            forward_signal(child.id(), seen_at_spawn, &mut warned);
+        }
+        if deadline.is_some_and(|d| Instant::now() >= d) {
+            // Like a signal, the timeout lets a working dpkg finish first.
+            if dpkg_running_in(child.id()) {
+                if !timed_out {
+                    color_print!(
+                        YELLOW,
+                        "\n⚠️  Timed out while dpkg is working; stopping once it finishes\n"
+                    );
+                }
+                timed_out = true;
+                thread::sleep(Duration::from_millis(100));
+                continue;
+            }
+            timed_out = true;
+            signal_group(child.id(), SIGTERM);
+            let grace = Instant::now() + KILL_GRACE;
+            while child.try_wait()?.is_none() && Instant::now() < grace {
+                thread::sleep(Duration::from_millis(100));
+            }
+            // Also reaches children that outlived the group leader.
+            signal_group(child.id(), SIGKILL);
+            break child.wait()?;
+        }
+        thread::sleep(Duration::from_millis(100));
+    };
+    // A daemon started by the step may hold its output open indefinitely.
+    let drained = Instant::now() + Duration::from_secs(2);
+    for _ in 0..streams {
+        let _ = done_rx.recv_timeout(drained.saturating_duration_since(Instant::now()));
+    }
+    let text = |kept: Option<Arc<Mutex<Vec<u8>>>>| {
+        kept.map(|kept| {
+            String::from_utf8_lossy(&kept.lock().unwrap_or_else(|e| e.into_inner())).into_owned()
+        })
+        .unwrap_or_default()
+    };
+    Ok(StepOutput {
+        status,
+        timed_out,
+        stdout: text(stdout),
+        stderr_tail: text(tail),
+    })
+}
+
+// Why a failed step is worth another attempt, or None when it is fatal.
+pub(crate) fn classify_failure(step: &Step) -> Option<&'static str> {
+    // A timed-out step already had its full allowance.
+    if step.timed_out || step.error.is_some() || step.exit_code.is_none() {
+        return None;
+    }
+    // A 404 means the package lists name files the mirror no longer has;
+    // waiting does not bring them back.
+    let lines: Vec<&str> = step
+        .stderr_tail
+        .lines()
+        .filter(|line| !line.contains("Not Found"))
+        .collect();
+    TRANSIENT_ERRORS
+        .iter()
+        .find(|(needle, _)| lines.iter().any(|line| line.contains(needle)))
+        .map(|(_, reason)| *reason)
+}
+
+// Append the inventory diff of a later attempt to the earlier ones', so each
+// package goes from its version before the first attempt to its version
+// after the last.
+pub(crate) fn merge_changes(earlier: &mut Vec<PackageChange>, later: Vec<PackageChange>) {
+    for change in later {
+        let Some(first) = earlier
+            .iter_mut()
+            .find(|c| c.backend == change.backend && c.name == change.name)
+        else {
+            earlier.push(change);
+            continue;
+        };
+        first.new_version = change.new_version;
+        first.action = match (&first.old_version, &first.new_version) {
+            (Some(_), Some(_)) => Action::Upgrade,
+            (None, _) => Action::Install,
+            (Some(_), None) => Action::Remove,
+        };
+    }
+    earlier.retain(|c| c.old_version != c.new_version);
+}
+
+// run(), repeated with exponential backoff while failures look transient.
+// The returned step spans every attempt.
+pub fn run_retrying(
+    cmd: &[String],
+    description: &str,
+    tracked: Option<&[Box<dyn PackageBackend>]>,
+) -> Step {
+    let retries = STEP_RETRIES.load(Ordering::Relaxed);
+    let mut earlier: Vec<PackageChange> = Vec::new();
+    let mut output = String::new();
+    let mut first_start: Option<SystemTime> = None;
+    let clock = Instant::now();
+    let mut attempt = 1;
+    loop {
+        let mut step = run(cmd, if attempt == 1 { description } else { "" }, tracked);
+        let started_at = *first_start.get_or_insert(step.started_at);
+        step.started_at = started_at;
+        step.duration = clock.elapsed();
+        step.attempts = attempt;
+        merge_changes(&mut earlier, mem::take(&mut step.packages));
+        step.packages = earlier.clone();
+        output.push_str(&step.output);
+        step.output = output.clone();
+
+        let reason = match classify_failure(&step) {
+            Some(reason) if !step.succeeded() && attempt <= retries && interrupted().is_none() => {
+                reason
+            }
+            _ => return step,
+        };
+        let delay = (RETRY_DELAY_SECS.load(Ordering::Relaxed) << (attempt - 1).min(16))
+            .min(MAX_RETRY_DELAY_SECS);
+        color_print!(
+            YELLOW,
+            "↻ {}; retrying in {} (attempt {} of {})\n",
+            reason,
+            format_secs(delay as f64),
+            attempt + 1,
+            retries + 1
+        );
+        let until = Instant::now() + Duration::from_secs(delay);
+        while Instant::now() < until {
+            if interrupted().is_some() {
+                return step;
+            }
+            thread::sleep(Duration::from_millis(100));
+        }
+        attempt += 1;
+    }
+}
+
+// "90", "90s", "30m", "2h"; "0" or "none" turn the limit off.
+pub fn parse_duration(text: &str) -> Result<u64, String> {
+    if text == "none" {
+        return Ok(0);
+    }
+    let split = text
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(text.len());
+    let (digits, unit) = text.split_at(split);
+    let scale = match unit {
+        "" | "s" => 1,
+        "m" => 60,
+        "h" => 3600,
+        _ => {
+            return Err(format!(
+                "Invalid duration: {text} (expected e.g. 90s, 30m or 2h)"
+            ))
+        }
+    };
+    digits
+        .parse::<u64>()
+        .map(|n| n * scale)
+        .map_err(|_| format!("Invalid duration: {text} (expected e.g. 90s, 30m or 2h)"))
+}
+
+// Run a read-only query and return its stdout, or None if it failed ----------
+pub fn capture(cmd: &[&str]) -> Option<String> {
+    let output = runner().output(&argv(cmd)).ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    Some(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+pub fn command_exists(name: &str) -> bool {
+    runner().exists(name)
+}
+
+// A scripted CommandRunner for unit tests.
+#[cfg(test)]
+pub(crate) mod testing {
+    use std::cell::RefCell;
+    use std::collections::VecDeque;
+    use std::io;
+    use std::os::unix::process::ExitStatusExt;
+    use std::process::{ExitStatus, Output};
+    use std::rc::Rc;
+    use std::sync::atomic::Ordering;
+
+    use super::{set_runner, CommandRunner, StepOutput, RETRY_DELAY_SECS};
+    use crate::backends::argv;
+
+    // argv prefix → exit codes with output.
+    type Script = Vec<(Vec<String>, VecDeque<(i32, String)>)>;
+
+    // Answers commands from a script keyed by argv prefix and records every
+    // call. A prefix with several replies hands them out in order, repeating
+    // the last; unscripted commands succeed silently.
+    #[derive(Default)]
+    pub(crate) struct FakeRunner {
+        replies: RefCell<Script>,
+        calls: RefCell<Vec<Vec<String>>>,
+        programs: Vec<&'static str>,
+    }
+
+    impl FakeRunner {
+        pub(crate) fn with_programs(programs: &[&'static str]) -> Self {
+            FakeRunner {
+                programs: programs.to_vec(),
+                ..FakeRunner::default()
+            }
+        }
+
+        // `text` is the command's stdout, and for steps also its stderr.
+        pub(crate) fn on(self, prefix: &[&str], replies: &[(i32, &str)]) -> Self {
+            let replies = replies.iter().map(|&(c, t)| (c, t.to_string())).collect();
+            self.replies.borrow_mut().push((argv(prefix), replies));
+            self
+        }
+
+        fn reply(&self, cmd: &[String]) -> (ExitStatus, String) {
+            self.calls.borrow_mut().push(cmd.to_vec());
+            let mut replies = self.replies.borrow_mut();
+            let (code, text) = match replies.iter_mut().find(|(p, _)| cmd.starts_with(p)) {
+                Some((_, queue)) if queue.len() > 1 => queue.pop_front().unwrap_or_default(),
+                Some((_, queue)) => queue.front().cloned().unwrap_or_default(),
+                None => (0, String::new()),
+            };
+            (ExitStatus::from_raw(code << 8), text)
+        }
+
+        pub(crate) fn calls(&self) -> Vec<String> {
+            self.calls.borrow().iter().map(|c| c.join(" ")).collect()
+        }
+    }
+
+    impl CommandRunner for Rc<FakeRunner> {
+        fn run(&self, cmd: &[String]) -> io::Result<StepOutput> {
+            let (status, text) = self.reply(cmd);
+            Ok(StepOutput {
+                status,
+                timed_out: false,
+                stdout: text.clone(),
+                stderr_tail: text,
+            })
+        }
+
+        fn output(&self, cmd: &[String]) -> io::Result<Output> {
+            let (status, stdout) = self.reply(cmd);
+            Ok(Output {
+                status,
+                stdout: stdout.into_bytes(),
+                stderr: Vec::new(),
+            })
+        }
+
+        fn exists(&self, program: &str) -> bool {
+            self.programs.contains(&program)
+        }
+    }
+
+    pub(crate) fn install_fake(fake: FakeRunner) -> Rc<FakeRunner> {
+        let fake = Rc::new(fake);
+        set_runner(Rc::new(Rc::clone(&fake)));
+        RETRY_DELAY_SECS.store(0, Ordering::Relaxed);
+        fake
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::testing::{install_fake, FakeRunner};
+    use super::*;
+    use crate::backends::argv;
+
+    #[test]
+    fn steps_record_argv_and_exit_code() {
+        let fake = install_fake(FakeRunner::default().on(&["apt-get", "update"], &[(100, "")]));
+        let step = run(&argv(&["apt-get", "update"]), "", None);
+        assert_eq!(step.exit_code, Some(100));
+        assert_eq!(step.failure_code(), 100);
+        assert!(!step.succeeded());
+        assert_eq!(fake.calls(), ["apt-get update"]);
+    }
+
+    #[test]
+    fn transient_failures_are_retried() {
+        let lock = "E: Could not get lock /var/lib/apt/lists/lock. It is held by process 4242";
+        let fake =
+            install_fake(FakeRunner::default().on(&["apt-get", "update"], &[(100, lock), (0, "")]));
+        let step = run_retrying(&argv(&["apt-get", "update"]), "", None);
+        assert!(step.succeeded());
+        assert_eq!(step.attempts, 2);
+        assert_eq!(fake.calls().len(), 2);
+    }
+
+    #[test]
+    fn fatal_failures_are_not_retried() {
+        let broken = "E: Unmet dependencies. Try 'apt --fix-broken install'";
+        let fake = install_fake(FakeRunner::default().on(&["apt-get"], &[(100, broken)]));
+        let step = run_retrying(&argv(&["apt-get", "-y", "dist-upgrade"]), "", None);
+        assert_eq!(step.attempts, 1);
+        assert_eq!(classify_failure(&step), None);
+        assert_eq!(fake.calls().len(), 1);
+    }
+
+    #[test]
+    fn missing_files_on_the_mirror_are_not_retried() {
+        let gone = "E: Failed to fetch http://archive.ubuntu.com/ubuntu/pool/main/h/hello/\
+                    hello_2.10-3_amd64.deb  404  Not Found [IP: 185.125.190.36 80]";
+        let fake = install_fake(FakeRunner::default().on(&["apt-get"], &[(100, gone)]));
+        let step = run_retrying(&argv(&["apt-get", "-y", "dist-upgrade"]), "", None);
+        assert_eq!(step.attempts, 1);
+        assert_eq!(fake.calls().len(), 1);
+        let timeout = "E: Failed to fetch http://archive.ubuntu.com/ubuntu/dists/noble/InRelease  \
+                       Connection failed [IP: 185.125.190.36 80]";
+        install_fake(FakeRunner::default().on(&["apt-get"], &[(100, timeout)]));
+        let step = run(&argv(&["apt-get", "update"]), "", None);
+        assert_eq!(classify_failure(&step), Some("mirror unreachable"));
+    }
+
+    #[test]
+    fn attempts_merge_their_package_changes() {
+        let change = |name: &str, action, old: Option<&str>, new: Option<&str>| PackageChange {
+            backend: "apt",
+            name: name.to_string(),
+            action,
+            old_version: old.map(str::to_string),
+            new_version: new.map(str::to_string),
+        };
+        let mut changes = vec![
+            change("libfoo1", Action::Upgrade, Some("1.0"), Some("1.1")),
+            change("libbar1", Action::Install, None, Some("2.0")),
+            change("oldlib1", Action::Remove, Some("0.9"), None),
+        ];
+        merge_changes(
+            &mut changes,
+            vec![
+                change("libfoo1", Action::Upgrade, Some("1.1"), Some("1.2")),
+                change("libbar1", Action::Remove, Some("2.0"), None),
+                change("oldlib1", Action::Install, None, Some("0.9")),
+                change("hello", Action::Upgrade, Some("2.10-2"), Some("2.10-3")),
+            ],
+        );
+        let merged: Vec<_> = changes
+            .iter()
+            .map(|c| {
+                (
+                    c.name.as_str(),
+                    c.action,
+                    c.old_version.as_deref(),
+                    c.new_version.as_deref(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            merged,
+            [
+                ("libfoo1", Action::Upgrade, Some("1.0"), Some("1.2")),
+                ("hello", Action::Upgrade, Some("2.10-2"), Some("2.10-3")),
+            ]
+        );
+    }
+}
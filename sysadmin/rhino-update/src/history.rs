@@ -0,0 +1,220 @@
+use crate::backends::PackageChange;
+use crate::changelog::{changelogs_from_json, print_changelogs};
+use crate::cli::Options;
+use crate::holds::held_back_status;
+use crate::json::{change_from_json, Json};
+use crate::report::write_report;
+use crate::rollback::snapshot_from_json;
+use crate::store::{list_runs, load_run};
+use crate::ui::{BOLD, CYAN, GREEN, MAGENTA, RED, YELLOW};
+
+// History and run details -----------------------------------------------------
+pub(crate) fn run_package_count(run: &Json) -> usize {
+    run.get("steps")
+        .as_arr()
+        .iter()
+        .map(|step| step.get("packages").as_arr().len())
+        .sum()
+}
+
+pub(crate) fn format_secs(secs: f64) -> String {
+    match secs {
+        s if s < 60.0 => format!("{s:.1}s"),
+        s if s < 3600.0 => format!("{}m{:02}s", (s / 60.0) as u64, (s % 60.0) as u64),
+        s => format!("{}h{:02}m", (s / 3600.0) as u64, (s % 3600.0 / 60.0) as u64),
+    }
+}
+
+pub(crate) fn format_change_versions(change: &PackageChange) -> String {
+    match (&change.old_version, &change.new_version) {
+        (Some(old), Some(new)) => format!("{old} → {new}"),
+        (None, Some(new)) => new.clone(),
+        (Some(old), None) => old.clone(),
+        (None, None) => String::new(),
+    }
+}
+
+pub fn cmd_history(opts: &Options) -> i32 {
+    let runs = match list_runs() {
+        Ok(runs) => runs,
+        Err(msg) => {
+            color_print!(RED, "❌ Cannot read run history: {}\n", msg);
+            return 1;
+        }
+    };
+    // RFC 3339 UTC timestamps compare correctly as strings.
+    let since = opts.since.as_deref().unwrap_or("");
+    let runs: Vec<&Json> = runs
+        .iter()
+        .filter(|run| run.get("started_at").as_str().unwrap_or("") >= since)
+        .take(opts.limit.unwrap_or(20))
+        .collect();
+
+    color_print!(
+        BOLD,
+        "{:<24} {:<20} {:<9} {:<8} {:>9} {:>8}\n",
+        "RUN ID",
+        "STARTED (UTC)",
+        "MODE",
+        "OUTCOME",
+        "DURATION",
+        "PACKAGES"
+    );
+    for run in &runs {
+        let outcome = run.get("outcome").as_str().unwrap_or("?");
+        let color = match outcome {
+            "success" => GREEN,
+            "failed" => RED,
+            _ => YELLOW,
+        };
+        let started = run.get("started_at").as_str().unwrap_or("?");
+        color_print!(
+            "",
+            "{:<24} {:<20} {:<9} ",
+            run.get("run_id").as_str().unwrap_or("?"),
+            started.trim_end_matches('Z').replace('T', " "),
+            run.get("mode").as_str().unwrap_or("?")
+        );
+        color_print!(color, "{:<8}", outcome);
+        color_print!(
+            "",
+            " {:>9} {:>8}\n",
+            format_secs(run.get("duration_secs").as_f64().unwrap_or(0.0)),
+            run_package_count(run)
+        );
+    }
+    if runs.is_empty() {
+        color_print!(YELLOW, "No recorded runs.\n");
+    }
+    0
+}
+
+pub fn cmd_show(opts: &Options, run_id: Option<&str>) -> i32 {
+    let run = match load_run(run_id, None) {
+        Ok(run) => run,
+        Err(msg) => {
+            color_print!(RED, "❌ Cannot load run: {}\n", msg);
+            return 1;
+        }
+    };
+    if opts.report {
+        write_report(&run, opts);
+        return 0;
+    }
+
+    let field = |key: &str| run.get(key).as_str().unwrap_or("-").to_string();
+    color_print!(format!("{MAGENTA}{BOLD}"), "🦏 Run {}\n\n", field("run_id"));
+    for key in ["host", "mode", "started_at", "finished_at", "outcome"] {
+        color_print!(CYAN, "  {:<12}", key);
+        color_print!("", " {}\n", field(key));
+    }
+    color_print!(CYAN, "  {:<12}", "duration");
+    color_print!(
+        "",
+        " {}\n",
+        format_secs(run.get("duration_secs").as_f64().unwrap_or(0.0))
+    );
+    if let Some(target) = run.get("rollback_of").as_str() {
+        color_print!(CYAN, "  {:<12}", "rollback_of");
+        color_print!("", " {}\n", target);
+    }
+    if let Some(snap) = snapshot_from_json(run.get("snapshot")) {
+        color_print!(CYAN, "  {:<12}", "snapshot");
+        color_print!("", " {} ({})\n", snap.name, snap.kind);
+    }
+
+    for step in run.get("steps").as_arr() {
+        let exit_code = step.get("exit_code").as_f64();
+        let ok = exit_code == Some(0.0) && step.get("error").as_str().is_none();
+        let (color, mark) = if ok { (GREEN, "✅") } else { (RED, "❌") };
+        color_print!(
+            color,
+            "\n{} {}",
+            mark,
+            step.get("description").as_str().unwrap_or("")
+        );
+        color_print!(
+            "",
+            " ({}, exit {})\n",
+            format_secs(step.get("duration_secs").as_f64().unwrap_or(0.0)),
+            exit_code.map_or_else(|| "-".to_string(), |c| c.to_string())
+        );
+        let cmd: Vec<&str> = step
+            .get("argv")
+            .as_arr()
+            .iter()
+            .filter_map(Json::as_str)
+            .collect();
+        color_print!(CYAN, "   ▶ ");
+        color_print!("", "{}\n", cmd.join(" "));
+        if let Some(error) = step.get("error").as_str() {
+            color_print!(RED, "   {}\n", error);
+        }
+        for change in step
+            .get("packages")
+            .as_arr()
+            .iter()
+            .filter_map(change_from_json)
+        {
+            color_print!(
+                "",
+                "     {:<8} {:<8} {} {}\n",
+                change.action.as_str(),
+                change.backend,
+                change.name,
+                format_change_versions(&change)
+            );
+        }
+    }
+    let planned: Vec<PackageChange> = run
+        .get("planned")
+        .as_arr()
+        .iter()
+        .filter_map(change_from_json)
+        .collect();
+    if !planned.is_empty() {
+        color_print!(BOLD, "\nPlanned changes ({}):\n", planned.len());
+        for change in &planned {
+            color_print!(
+                "",
+                "     {:<8} {:<8} {} {}\n",
+                change.action.as_str(),
+                change.backend,
+                change.name,
+                format_change_versions(change)
+            );
+        }
+    }
+    let held_back: Vec<(PackageChange, &str)> = run
+        .get("held_back")
+        .as_arr()
+        .iter()
+        .filter_map(|entry| {
+            let change = change_from_json(entry)?;
+            // Records from before the status was kept.
+            let status = entry
+                .get("status")
+                .as_str()
+                .unwrap_or(held_back_status(&change));
+            Some((change, status))
+        })
+        .collect();
+    if !held_back.is_empty() {
+        color_print!(BOLD, "\nHeld back ({}):\n", held_back.len());
+        for (change, status) in &held_back {
+            color_print!(
+                "",
+                "     {:<8} {} {} ({})\n",
+                change.backend,
+                change.name,
+                format_change_versions(change),
+                status
+            );
+        }
+    }
+    if opts.changelogs {
+        color_print!("", "\n");
+        print_changelogs(&changelogs_from_json(&run));
+    }
+    0
+}
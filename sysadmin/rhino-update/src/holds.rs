@@ -0,0 +1,232 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::backends::{PackageBackend, PackageChange, BACKEND_NAMES};
+use crate::cli::Options;
+use crate::exec::{run, Step};
+use crate::history::format_change_versions;
+use crate::privilege::require_root;
+use crate::report::RunReport;
+use crate::ui::{CYAN, GREEN, RED, YELLOW};
+
+// Package holds ---------------------------------------------------------------
+// Holds are written [BACKEND:]NAME, apt when the prefix is omitted, both for
+// `hold add/remove` and in --hold/`holds`. apt uses apt-mark, flatpak masks
+// and snap `refresh --hold`; pacstall has no hold of its own, so rhino-update
+// keeps the list, and skips pacstall's upgrade while anything is on it.
+pub(crate) const PACSTALL_HOLDS: &str = "/etc/rhino-update/pacstall-holds";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HoldAction {
+    Add,
+    Remove,
+    List,
+}
+
+// apt names may carry an architecture ("libc6:i386"), so only a known
+// backend counts as a prefix.
+pub(crate) fn parse_hold(spec: &str) -> Result<(&str, &str), String> {
+    let (backend, name) = match spec.split_once(':') {
+        Some((backend, name)) if BACKEND_NAMES.contains(&backend) => (backend, name),
+        _ => ("apt", spec),
+    };
+    if name.is_empty() {
+        return Err(format!("Invalid hold: {spec} (expected [BACKEND:]PACKAGE)"));
+    }
+    Ok((backend, name))
+}
+
+pub(crate) fn holds_by_backend(specs: &[String]) -> BTreeMap<&str, Vec<String>> {
+    let mut grouped: BTreeMap<&str, Vec<String>> = BTreeMap::new();
+    for (backend, name) in specs.iter().filter_map(|spec| parse_hold(spec).ok()) {
+        grouped.entry(backend).or_default().push(name.to_string());
+    }
+    grouped
+}
+
+pub(crate) fn pacstall_holds() -> Vec<String> {
+    fs::read_to_string(PACSTALL_HOLDS)
+        .unwrap_or_default()
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(str::to_string)
+        .collect()
+}
+
+pub(crate) fn write_pacstall_holds(holds: &[String]) -> io::Result<()> {
+    if let Some(dir) = Path::new(PACSTALL_HOLDS).parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let mut contents = String::from("# pacstall packages rhino-update will not upgrade\n");
+    for pkg in holds {
+        contents.push_str(pkg);
+        contents.push('\n');
+    }
+    fs::write(PACSTALL_HOLDS, contents)
+}
+
+// Add or release holds on one backend; Some(step) when a command ran.
+pub(crate) fn set_holds(
+    backend: &dyn PackageBackend,
+    packages: &[String],
+    hold: bool,
+) -> Result<Option<Step>, String> {
+    if let Some(cmd) = backend.hold_command(packages, hold) {
+        let verb = if hold { "Holding" } else { "Releasing" };
+        let mut step = run(&cmd, &format!("{verb} {} packages …", backend.name()), None);
+        step.backend = Some(backend.name());
+        return Ok(Some(step));
+    }
+    if backend.name() != "pacstall" {
+        return Err(format!("{} cannot hold packages", backend.name()));
+    }
+    let mut holds = pacstall_holds();
+    holds.retain(|pkg| !packages.contains(pkg));
+    if hold {
+        holds.extend(packages.iter().cloned());
+    }
+    write_pacstall_holds(&holds).map_err(|e| format!("{PACSTALL_HOLDS}: {e}"))?;
+    Ok(None)
+}
+
+// Make sure every configured hold is in place. Only missing holds are
+// applied, so the run records real changes.
+pub(crate) fn apply_holds(
+    report: &mut RunReport,
+    backends: &[Box<dyn PackageBackend>],
+    holds: &[String],
+) -> Result<(), i32> {
+    for (name, wanted) in holds_by_backend(holds) {
+        let Some(backend) = backends
+            .iter()
+            .find(|b| b.name() == name && b.is_available())
+        else {
+            continue;
+        };
+        let held = backend.list_holds().unwrap_or_default();
+        let missing: Vec<String> = wanted
+            .into_iter()
+            .filter(|pkg| !held.contains(pkg))
+            .collect();
+        if missing.is_empty() {
+            continue;
+        }
+        match set_holds(backend.as_ref(), &missing, true) {
+            Ok(Some(step)) => {
+                let code = (!step.succeeded()).then(|| step.failure_code());
+                report.steps.push(step);
+                if let Some(code) = code {
+                    return Err(code);
+                }
+            }
+            Ok(None) => color_print!(GREEN, "🔒 Held {}: {}\n", name, missing.join(", ")),
+            Err(msg) => {
+                color_print!(RED, "❌ {}\n", msg);
+                return Err(1);
+            }
+        }
+    }
+    Ok(())
+}
+
+// Whether a held package really has an upgrade waiting. pacstall cannot
+// tell, so its held packages are listed with no new version.
+pub(crate) fn held_back_status(change: &PackageChange) -> &'static str {
+    if change.new_version.is_some() {
+        "upgrade available"
+    } else {
+        "held, upgrade status unknown"
+    }
+}
+
+// Held packages whose upgrade this run skipped.
+pub(crate) fn record_held_back(report: &mut RunReport, backends: &[&dyn PackageBackend]) {
+    for backend in backends {
+        if let Ok(held) = backend.list_holds() {
+            report.held_back.extend(backend.held_upgrades(&held));
+        }
+    }
+    if report.held_back.is_empty() {
+        return;
+    }
+    color_print!(YELLOW, "🔒 Held back:\n");
+    for change in &report.held_back {
+        color_print!(CYAN, "  {:<8}", change.backend);
+        color_print!(
+            "",
+            " {} {} ({})\n",
+            change.name,
+            format_change_versions(change),
+            held_back_status(change)
+        );
+    }
+    color_print!("", "\n");
+}
+
+pub fn cmd_hold(
+    opts: &Options,
+    backends: &[Box<dyn PackageBackend>],
+    action: HoldAction,
+    packages: &[String],
+) -> i32 {
+    if action == HoldAction::List {
+        for backend in backends.iter().filter(|b| b.is_available()) {
+            match backend.list_holds() {
+                Ok(held) if held.is_empty() => {
+                    color_print!(CYAN, "  {:<8}", backend.name());
+                    color_print!("", " (none)\n");
+                }
+                Ok(held) => {
+                    for pkg in held {
+                        color_print!(CYAN, "  {:<8}", backend.name());
+                        color_print!("", " {}\n", pkg);
+                    }
+                }
+                Err(msg) => color_print!(YELLOW, "  {:<8} {}\n", backend.name(), msg),
+            }
+        }
+        return 0;
+    }
+
+    if packages.is_empty() {
+        color_print!(RED, "❌ hold add/remove needs at least one package\n");
+        return 2;
+    }
+    if let Err(code) = require_root(opts) {
+        return code;
+    }
+    let hold = action == HoldAction::Add;
+    let mut failed = false;
+    for (name, pkgs) in holds_by_backend(packages) {
+        let Some(backend) = backends
+            .iter()
+            .find(|b| b.name() == name && b.is_available())
+        else {
+            color_print!(RED, "❌ {} is not available\n", name);
+            failed = true;
+            continue;
+        };
+        match set_holds(backend.as_ref(), &pkgs, hold) {
+            Ok(Some(step)) if !step.succeeded() => failed = true,
+            Ok(_) if hold => color_print!(GREEN, "🔒 Held {}: {}\n", name, pkgs.join(", ")),
+            Ok(_) => color_print!(GREEN, "🔓 Released {}: {}\n", name, pkgs.join(", ")),
+            Err(msg) => {
+                color_print!(RED, "❌ {}\n", msg);
+                failed = true;
+            }
+        }
+    }
+    if !hold {
+        for spec in packages.iter().filter(|spec| opts.holds.contains(spec)) {
+            color_print!(
+                YELLOW,
+                "⚠️  {} is still in the configured holds; the next update will hold it again\n",
+                spec
+            );
+        }
+    }
+    i32::from(failed)
+}
@@ -0,0 +1,56 @@
+use crate::backends::argv;
+use crate::exec::run;
+use crate::report::RunReport;
+
+// Hooks -----------------------------------------------------------------------
+// Shell commands from the config or --pre-hook/--post-hook. They see the run
+// in RHINO_UPDATE_RUN_ID (and its `outcome` in RHINO_UPDATE_OUTCOME for post
+// hooks), set through env(1) for the hook alone.
+pub fn run_hooks(
+    report: &mut RunReport,
+    hooks: &[String],
+    stage: &str,
+    outcome: Option<&str>,
+) -> Result<(), i32> {
+    for hook in hooks {
+        let mut cmd = vec![
+            "env".to_string(),
+            format!("RHINO_UPDATE_RUN_ID={}", report.run_id),
+        ];
+        if let Some(outcome) = outcome {
+            cmd.push(format!("RHINO_UPDATE_OUTCOME={outcome}"));
+        }
+        cmd.extend(argv(&["sh", "-c", hook]));
+        let step = run(&cmd, &format!("Running {stage} hook …"), None);
+        let code = (!step.succeeded()).then(|| step.failure_code());
+        report.steps.push(step);
+        if let Some(code) = code {
+            return Err(code);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::exec::testing::{install_fake, FakeRunner};
+
+    #[test]
+    fn hooks_get_the_run_in_their_own_environment() {
+        let fake = install_fake(FakeRunner::default().on(&["env"], &[(0, "")]));
+        let mut report = RunReport::new("update");
+        let hooks = ["logger done".to_string()];
+        assert_eq!(
+            run_hooks(&mut report, &hooks, "post-update", Some("success")),
+            Ok(())
+        );
+        assert_eq!(
+            fake.calls(),
+            [format!(
+                "env RHINO_UPDATE_RUN_ID={} RHINO_UPDATE_OUTCOME=success sh -c logger done",
+                report.run_id()
+            )]
+        );
+    }
+}
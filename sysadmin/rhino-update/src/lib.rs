@@ -0,0 +1,40 @@
+// rhino_update – the update engine behind the rhino-update command, for tools
+// that want to run or inspect Rhino Linux updates themselves.
+//
+// cli::run is the whole command. To drive the pipeline directly, build
+// Options with cli::load_options (the same flags the command takes) or as a
+// struct, apply them with cli::configure and call update::cmd_update,
+// plan::cmd_plan or rollback::cmd_rollback, which return the run's
+// report::RunReport; each run is recorded under store::runs_dir(). The
+// package managers are reached through backends::PackageBackend, and every
+// external command through the thread's exec::CommandRunner (see
+// exec::set_runner). Output goes through color_print! and the ui module.
+
+#[macro_use]
+pub mod ui;
+
+pub mod backends;
+pub mod changelog;
+pub mod cli;
+pub mod config;
+pub mod exec;
+pub mod history;
+pub mod holds;
+pub mod hooks;
+pub mod json;
+pub mod log;
+pub mod plan;
+pub mod preflight;
+pub mod privilege;
+pub mod reboot;
+pub mod report;
+pub mod rollback;
+pub mod schedule;
+pub mod services;
+pub mod signals;
+pub mod snapshot;
+pub mod store;
+pub mod summary;
+pub mod time;
+pub mod update;
+pub mod window;
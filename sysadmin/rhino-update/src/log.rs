@@ -0,0 +1,245 @@
+use std::env;
+use std::fs;
+use std::io::{self, Write};
+use std::os::unix::net::UnixDatagram;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::SystemTime;
+
+use crate::exec::Step;
+use crate::history::format_change_versions;
+use crate::report::RunReport;
+use crate::time::format_timestamp;
+use crate::ui::{strip_ansi, RED, YELLOW};
+
+// Logging ---------------------------------------------------------------------
+// Runs that change the system append every line they print, plus one record
+// per step and package change, to LOG_FILE ("<time> [LEVEL] [RUN_ID] text
+// key=value…"). The same records go to journald with RUN_ID, STEP, EXIT_CODE
+// and PACKAGE fields, e.g. `journalctl -t rhino-update RUN_ID=<id>`. Under
+// systemd stdout already reaches the journal, so printed lines are not sent
+// twice.
+pub const LOG_FILE: &str = "/var/log/rhino-update.log";
+pub(crate) const LOG_MAX_BYTES: u64 = 5 * 1024 * 1024;
+pub(crate) const LOG_ROTATIONS: usize = 4;
+pub(crate) const JOURNAL_SOCKET: &str = "/run/systemd/journal/socket";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Level {
+    Debug,
+    Info,
+    Warning,
+    Error,
+}
+
+impl Level {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Level::Debug => "DEBUG",
+            Level::Info => "INFO",
+            Level::Warning => "WARNING",
+            Level::Error => "ERROR",
+        }
+    }
+
+    // syslog(3) priorities, as journald expects them.
+    pub fn priority(self) -> u8 {
+        match self {
+            Level::Debug => 7,
+            Level::Info => 6,
+            Level::Warning => 4,
+            Level::Error => 3,
+        }
+    }
+}
+
+pub(crate) struct Logger {
+    pub(crate) file: Option<fs::File>,
+    pub(crate) journal: Option<UnixDatagram>,
+    pub(crate) journal_has_stdout: bool,
+    pub(crate) run_id: String,
+    pub(crate) step: u32,
+    // Printed text up to the next newline.
+    pub(crate) pending: String,
+}
+
+pub(crate) static LOGGER: Mutex<Option<Logger>> = Mutex::new(None);
+
+// log → log.1 → … → log.LOG_ROTATIONS, dropping the oldest.
+pub(crate) fn rotate_log(path: &Path) -> io::Result<()> {
+    if fs::metadata(path).map_or(true, |m| m.len() < LOG_MAX_BYTES) {
+        return Ok(());
+    }
+    let numbered = |n: usize| PathBuf::from(format!("{}.{n}", path.display()));
+    for n in (1..LOG_ROTATIONS).rev() {
+        if numbered(n).exists() {
+            fs::rename(numbered(n), numbered(n + 1))?;
+        }
+    }
+    fs::rename(path, numbered(1))
+}
+
+pub fn init_logging(path: &str, report: &RunReport) {
+    let path = Path::new(path);
+    if let Err(e) = rotate_log(path) {
+        color_print!(YELLOW, "⚠️  Could not rotate {}: {}\n", path.display(), e);
+    }
+    let file = fs::OpenOptions::new().create(true).append(true).open(path);
+    if let Err(e) = &file {
+        color_print!(YELLOW, "⚠️  Not logging to {}: {}\n", path.display(), e);
+    }
+    let journal = UnixDatagram::unbound()
+        .and_then(|sock| sock.connect(JOURNAL_SOCKET).map(|_| sock))
+        .ok();
+    *LOGGER.lock().unwrap_or_else(|e| e.into_inner()) = Some(Logger {
+        file: file.ok(),
+        journal,
+        journal_has_stdout: env::var_os("JOURNAL_STREAM").is_some(),
+        run_id: report.run_id.clone(),
+        step: 0,
+        pending: String::new(),
+    });
+    log(Level::Info, &format!("{} run started", report.mode), &[]);
+}
+
+// journald's native protocol: KEY=value lines, or KEY\n<u64 length>value for
+// values spanning several lines.
+pub(crate) fn journal_field(buf: &mut Vec<u8>, key: &str, value: &str) {
+    buf.extend_from_slice(key.as_bytes());
+    if value.contains('\n') {
+        buf.push(b'\n');
+        buf.extend_from_slice(&(value.len() as u64).to_le_bytes());
+    } else {
+        buf.push(b'=');
+    }
+    buf.extend_from_slice(value.as_bytes());
+    buf.push(b'\n');
+}
+
+impl Logger {
+    pub(crate) fn write(
+        &mut self,
+        level: Level,
+        message: &str,
+        fields: &[(&str, String)],
+        to_journal: bool,
+    ) {
+        if let Some(file) = &mut self.file {
+            let mut line = format!(
+                "{} [{}] [{}] {}",
+                format_timestamp(SystemTime::now()),
+                level.as_str(),
+                self.run_id,
+                message
+            );
+            for (key, value) in fields {
+                line.push_str(&format!(" {}={}", key.to_ascii_lowercase(), value));
+            }
+            line.push('\n');
+            let _ = file.write_all(line.as_bytes());
+        }
+        if let Some(journal) = self.journal.as_ref().filter(|_| to_journal) {
+            let mut buf = Vec::new();
+            journal_field(&mut buf, "MESSAGE", message);
+            journal_field(&mut buf, "PRIORITY", &level.priority().to_string());
+            journal_field(&mut buf, "SYSLOG_IDENTIFIER", "rhino-update");
+            journal_field(&mut buf, "RUN_ID", &self.run_id);
+            for (key, value) in fields {
+                journal_field(&mut buf, key, value);
+            }
+            let _ = journal.send(&buf);
+        }
+    }
+}
+
+pub fn log(level: Level, message: &str, fields: &[(&str, String)]) {
+    if let Some(logger) = LOGGER.lock().unwrap_or_else(|e| e.into_inner()).as_mut() {
+        logger.write(level, message, fields, true);
+    }
+}
+
+// Mirror printed text line by line; red lines are errors, yellow ones warnings.
+pub(crate) fn log_printed(text: &str) {
+    let mut guard = LOGGER.lock().unwrap_or_else(|e| e.into_inner());
+    let Some(logger) = guard.as_mut() else {
+        return;
+    };
+    logger.pending.push_str(text);
+    while let Some(end) = logger.pending.find('\n') {
+        let line: String = logger.pending.drain(..=end).collect();
+        let level = if line.contains(RED) {
+            Level::Error
+        } else if line.contains(YELLOW) {
+            Level::Warning
+        } else {
+            Level::Info
+        };
+        let plain = strip_ansi(&line);
+        if !plain.trim().is_empty() {
+            let to_journal = !logger.journal_has_stdout;
+            logger.write(level, plain.trim_end(), &[], to_journal);
+        }
+    }
+}
+
+// Number the step and record its start; the number goes back into log_step.
+pub(crate) fn log_step_start(cmd: &[String]) -> u32 {
+    let mut guard = LOGGER.lock().unwrap_or_else(|e| e.into_inner());
+    let Some(logger) = guard.as_mut() else {
+        return 0;
+    };
+    logger.step += 1;
+    let step = logger.step;
+    logger.write(
+        Level::Debug,
+        &format!("step {step} started: {}", cmd.join(" ")),
+        &[("STEP", step.to_string())],
+        true,
+    );
+    step
+}
+
+pub(crate) fn log_step(number: u32, step: &Step) {
+    let level = if step.succeeded() {
+        Level::Info
+    } else {
+        Level::Error
+    };
+    let exit_code = step
+        .exit_code
+        .map_or_else(|| "none".to_string(), |c| c.to_string());
+    let fields = [
+        ("STEP", number.to_string()),
+        ("EXIT_CODE", exit_code),
+        ("DURATION", format!("{:.1}", step.duration.as_secs_f64())),
+    ];
+    let outcome = step.error.clone().unwrap_or_else(|| {
+        if step.succeeded() {
+            "succeeded".to_string()
+        } else {
+            "failed".to_string()
+        }
+    });
+    log(
+        level,
+        &format!("step {number} {outcome}: {}", step.argv.join(" ")),
+        &fields,
+    );
+    for change in &step.packages {
+        log(
+            Level::Info,
+            &format!(
+                "{} {} {}",
+                change.action.as_str(),
+                change.name,
+                format_change_versions(change)
+            ),
+            &[
+                ("STEP", number.to_string()),
+                ("PACKAGE", change.name.clone()),
+                ("BACKEND", change.backend.to_string()),
+                ("ACTION", change.action.as_str().to_string()),
+            ],
+        );
+    }
+}
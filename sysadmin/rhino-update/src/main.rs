@@ -0,0 +1,23 @@
+// rhino-update – colourful one-shot update & cleanup for Rhino Linux
+// Needs root (except for --dry-run, which only queries the backends) and
+// re-runs itself through sudo or pkexec when started without it.
+//
+// Usage: rhino-update [--dry-run | plan] [--backend LIST] [--security-only]
+//                     [--snapshot[=auto|timeshift|btrfs]]
+//                     [--reboot=never|if-needed|at HH:MM]
+//                     [--report json [--report-file PATH]] [--changelogs]
+//                     [--config PATH] [--profile NAME]
+//        rhino-update rollback [RUN_ID] [--dry-run]
+//        rhino-update history [--limit N] [--since DATE]
+//        rhino-update show [RUN_ID]
+//        rhino-update schedule install|status|remove [--calendar SPEC]
+//                     [--randomized-delay SPAN]
+//        rhino-update [--window SPEC]... [--timezone TZ] [--blackout DATES]
+//        rhino-update hold add|remove [BACKEND:]PACKAGE...
+//        rhino-update hold list [--backend LIST]
+//        rhino-update cves [RUN_ID|last|pending] [--cve-feed PATH] [--report json]
+
+fn main() {
+    let args = std::env::args().skip(1).collect();
+    std::process::exit(rhino_update::cli::run(args));
+}
@@ -0,0 +1,111 @@
+use std::io::{self, Write};
+
+use crate::backends::{Action, PackageBackend, PackageChange};
+use crate::changelog::{collect_changelogs, print_changelogs};
+use crate::cli::Options;
+use crate::history::format_change_versions;
+use crate::report::{emit_report, RunReport};
+use crate::ui::{BLUE, BOLD, CYAN, GREEN, MAGENTA, RED, YELLOW};
+
+// Dry-run planning ------------------------------------------------------------
+#[derive(Debug)]
+pub struct BackendPlan {
+    pub backend: &'static str,
+    pub available: bool,
+    pub changes: Vec<PackageChange>,
+    pub notes: Vec<String>,
+}
+
+pub fn plan_backend(backend: &dyn PackageBackend, security_only: bool) -> BackendPlan {
+    let mut plan = BackendPlan {
+        backend: backend.name(),
+        available: backend.is_available(),
+        changes: Vec::new(),
+        notes: Vec::new(),
+    };
+    if !plan.available {
+        return plan;
+    }
+    if security_only && !backend.has_security_channel() {
+        plan.notes.push(format!(
+            "{} has no security channel; skipped with --security-only",
+            backend.name()
+        ));
+        return plan;
+    }
+    let upgrades = if security_only {
+        backend.list_security_upgrades()
+    } else {
+        backend.list_upgradable()
+    };
+    for result in [upgrades, backend.list_orphans()] {
+        match result {
+            Ok(changes) => plan.changes.extend(changes),
+            Err(note) => plan.notes.push(note),
+        }
+    }
+    plan
+}
+
+pub fn print_plan(plans: &[BackendPlan]) {
+    let mut total = 0;
+    for plan in plans {
+        color_print!(format!("{BLUE}{BOLD}"), "➜ {}\n", plan.backend);
+        if !plan.available {
+            color_print!(YELLOW, "  not installed, skipped\n");
+            continue;
+        }
+        for note in &plan.notes {
+            color_print!(CYAN, "  ℹ️  {}\n", note);
+        }
+        if plan.changes.is_empty() {
+            color_print!(GREEN, "  nothing to do\n");
+        }
+        for action in [Action::Upgrade, Action::Install, Action::Remove] {
+            for change in plan.changes.iter().filter(|c| c.action == action) {
+                let color = match action {
+                    Action::Upgrade => GREEN,
+                    Action::Install => CYAN,
+                    Action::Remove => RED,
+                };
+                color_print!(color, "  {:<8}", action.as_str());
+                color_print!("", " {} {}\n", change.name, format_change_versions(change));
+            }
+        }
+        total += plan.changes.len();
+    }
+    color_print!(
+        format!("{MAGENTA}{BOLD}"),
+        "\n📋 {} change(s) planned; nothing was modified.\n",
+        total
+    );
+}
+
+pub fn cmd_plan(opts: &Options, backends: &[Box<dyn PackageBackend>]) -> RunReport {
+    let mut report = RunReport::new("dry-run");
+    color_print!(
+        format!("{MAGENTA}{BOLD}"),
+        "🦏 Rhino Linux Update Plan (dry-run)\n\n"
+    );
+    color_print!(
+        CYAN,
+        "ℹ️  Based on the current package metadata (not refreshed in dry-run)\n\n"
+    );
+    let plans: Vec<BackendPlan> = backends
+        .iter()
+        .map(|b| plan_backend(b.as_ref(), opts.security_only))
+        .collect();
+    print_plan(&plans);
+    report.backends = plans.iter().map(|p| (p.backend, p.available)).collect();
+    report.planned = plans.into_iter().flat_map(|p| p.changes).collect();
+    if opts.changelogs {
+        // Only upgrades whose .deb apt has already downloaded can be shown.
+        color_print!("", "\n");
+        report.changelogs = collect_changelogs(&report.planned);
+        print_changelogs(&report.changelogs);
+    }
+    report.finish("success", 0);
+    emit_report(&report, opts);
+    let _ = io::stdout().flush();
+    report
+}
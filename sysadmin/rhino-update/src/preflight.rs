@@ -0,0 +1,379 @@
+use std::fs;
+
+use crate::cli::Options;
+use crate::exec::capture;
+use crate::json::Json;
+use crate::report::RunReport;
+use crate::ui::{BLUE, BOLD, GREEN, RED, YELLOW};
+
+// Pre-flight checks -----------------------------------------------------------
+pub const EXIT_PREFLIGHT: i32 = 3;
+pub(crate) const MIN_FREE_ROOT: u64 = 1 << 30;
+pub(crate) const MIN_FREE_VAR_CACHE: u64 = 1 << 30;
+pub(crate) const MIN_FREE_BOOT: u64 = 64 << 20;
+
+pub(crate) const DPKG_LOCKS: [&str; 4] = [
+    "/var/lib/dpkg/lock-frontend",
+    "/var/lib/dpkg/lock",
+    "/var/lib/apt/lists/lock",
+    "/var/cache/apt/archives/lock",
+];
+
+// Process names (/proc/PID/comm, max 15 chars) of other package managers.
+pub(crate) const PACKAGE_MANAGERS: [&str; 10] = [
+    "apt",
+    "apt-get",
+    "aptitude",
+    "dpkg",
+    "unattended-upgr",
+    "synaptic",
+    "nala",
+    "rpk",
+    "pacstall",
+    "flatpak",
+];
+
+// flatpak also runs sandboxed apps (`flatpak run`, `flatpak enter`, …);
+// only these subcommands change installations.
+pub(crate) const FLATPAK_MUTATING: [&str; 6] = [
+    "install",
+    "update",
+    "upgrade",
+    "uninstall",
+    "remove",
+    "repair",
+];
+
+// Whether a package manager process with this NUL-separated
+// /proc/PID/cmdline is changing packages rather than, say, running an app.
+pub(crate) fn changes_packages(name: &str, cmdline: &str) -> bool {
+    if name != "flatpak" {
+        return true;
+    }
+    cmdline
+        .split('\0')
+        .skip(1)
+        .find(|arg| !arg.starts_with('-'))
+        .is_some_and(|subcommand| FLATPAK_MUTATING.contains(&subcommand))
+}
+
+#[derive(Clone, Debug)]
+pub struct Check {
+    pub category: &'static str,
+    pub passed: bool,
+    pub detail: String,
+}
+
+impl From<&Check> for Json {
+    fn from(check: &Check) -> Json {
+        Json::obj([
+            ("category", check.category.into()),
+            ("passed", Json::Bool(check.passed)),
+            ("detail", check.detail.as_str().into()),
+        ])
+    }
+}
+
+pub(crate) fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KiB", "MiB", "GiB", "TiB"];
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit + 1 < UNITS.len() {
+        value /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{bytes} B")
+    } else {
+        format!("{value:.1} {}", UNITS[unit])
+    }
+}
+
+// Available bytes on the filesystem holding `path`, from POSIX `df` output.
+pub(crate) fn free_bytes(path: &str) -> Option<u64> {
+    let out = capture(&["df", "-Pk", path])?;
+    let kib: u64 = out
+        .lines()
+        .nth(1)?
+        .split_whitespace()
+        .nth(3)?
+        .parse()
+        .ok()?;
+    Some(kib * 1024)
+}
+
+pub(crate) fn check_free_space(path: &str, needed: u64) -> Check {
+    match free_bytes(path) {
+        Some(free) => Check {
+            category: "disk-space",
+            passed: free >= needed,
+            detail: format!(
+                "{path}: {} free, {} required",
+                format_bytes(free),
+                format_bytes(needed)
+            ),
+        },
+        None => Check {
+            category: "disk-space",
+            passed: true,
+            detail: format!("{path}: free space unknown, not checked"),
+        },
+    }
+}
+
+// Size of the newest installed kernel with its initrd, map and config: roughly
+// what the next kernel upgrade will add to /boot.
+pub(crate) fn newest_kernel_footprint() -> Option<(String, u64)> {
+    let newest = fs::read_dir("/boot")
+        .ok()?
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| {
+            let name = entry.file_name().into_string().ok()?;
+            let version = name.strip_prefix("vmlinuz-")?.to_string();
+            Some((entry.metadata().ok()?.modified().ok()?, version))
+        })
+        .max()?
+        .1;
+    let size = ["vmlinuz", "initrd.img", "System.map", "config"]
+        .iter()
+        .filter_map(|prefix| fs::metadata(format!("/boot/{prefix}-{newest}")).ok())
+        .map(|meta| meta.len())
+        .sum();
+    Some((newest, size))
+}
+
+pub(crate) fn check_boot_capacity() -> Check {
+    let Some((version, footprint)) = newest_kernel_footprint() else {
+        return Check {
+            category: "boot",
+            passed: true,
+            detail: "no kernels in /boot, not checked".into(),
+        };
+    };
+    let needed = footprint.max(MIN_FREE_BOOT);
+    let mut check = check_free_space("/boot", needed);
+    check.category = "boot";
+    check.detail = format!(
+        "{} (kernel {version} uses {})",
+        check.detail,
+        format_bytes(footprint)
+    );
+    check
+}
+
+// dpkg and apt take fcntl locks; a held lock shows up in /proc/locks with
+// the lock file's inode.
+pub(crate) fn lock_holder(path: &str, locks: &str) -> Option<u32> {
+    use std::os::unix::fs::MetadataExt;
+    let inode = fs::metadata(path).ok()?.ino();
+    locks.lines().find_map(|line| {
+        let cols: Vec<&str> = line.split_whitespace().collect();
+        let (pid, id) = match cols.as_slice() {
+            [_, _, _, _, pid, id, ..] if !line.contains("->") => (pid, id),
+            _ => return None,
+        };
+        let lock_inode: u64 = id.rsplit(':').next()?.parse().ok()?;
+        (lock_inode == inode).then(|| pid.parse().ok()).flatten()
+    })
+}
+
+pub(crate) fn process_name(pid: u32) -> String {
+    fs::read_to_string(format!("/proc/{pid}/comm"))
+        .map(|comm| comm.trim().to_string())
+        .unwrap_or_else(|_| "?".into())
+}
+
+pub(crate) fn check_locks() -> Check {
+    let locks = fs::read_to_string("/proc/locks").unwrap_or_default();
+    let held: Vec<String> = DPKG_LOCKS
+        .iter()
+        .filter_map(|path| {
+            let pid = lock_holder(path, &locks)?;
+            Some(format!("{path} held by {} (pid {pid})", process_name(pid)))
+        })
+        .collect();
+    Check {
+        category: "lock",
+        passed: held.is_empty(),
+        detail: if held.is_empty() {
+            "dpkg/apt locks are free".into()
+        } else {
+            held.join("; ")
+        },
+    }
+}
+
+// Our own process and its ancestors (e.g. the `rpk` or sudo that started us)
+// do not count as competing package managers.
+pub(crate) fn ancestor_pids() -> Vec<u32> {
+    let mut pids = vec![std::process::id()];
+    while let Some(&pid) = pids.last() {
+        let parent = fs::read_to_string(format!("/proc/{pid}/stat"))
+            .ok()
+            .and_then(|stat| {
+                let rest = stat.rsplit_once(')')?.1;
+                rest.split_whitespace().nth(1)?.parse::<u32>().ok()
+            });
+        match parent {
+            Some(ppid) if ppid > 1 && !pids.contains(&ppid) => pids.push(ppid),
+            _ => break,
+        }
+    }
+    pids
+}
+
+pub(crate) fn check_package_managers() -> Check {
+    let ours = ancestor_pids();
+    let mut running: Vec<String> = fs::read_dir("/proc")
+        .map(|entries| {
+            entries
+                .filter_map(|entry| entry.ok()?.file_name().to_str()?.parse::<u32>().ok())
+                .filter(|pid| !ours.contains(pid))
+                .map(|pid| (pid, process_name(pid)))
+                .filter(|(pid, name)| {
+                    PACKAGE_MANAGERS.contains(&name.as_str())
+                        && changes_packages(
+                            name,
+                            &fs::read_to_string(format!("/proc/{pid}/cmdline")).unwrap_or_default(),
+                        )
+                })
+                .map(|(pid, name)| format!("{name} (pid {pid})"))
+                .collect()
+        })
+        .unwrap_or_default();
+    running.sort();
+    Check {
+        category: "concurrent-manager",
+        passed: running.is_empty(),
+        detail: if running.is_empty() {
+            "no other package manager is running".into()
+        } else {
+            format!("already running: {}", running.join(", "))
+        },
+    }
+}
+
+pub(crate) fn check_power() -> Check {
+    let supplies: Vec<(String, String)> = fs::read_dir("/sys/class/power_supply")
+        .map(|entries| {
+            entries
+                .filter_map(|entry| entry.ok())
+                .map(|entry| {
+                    let read = |f: &str| {
+                        fs::read_to_string(entry.path().join(f))
+                            .map(|v| v.trim().to_string())
+                            .unwrap_or_default()
+                    };
+                    (read("type"), read("online"))
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    let has_battery = supplies.iter().any(|(kind, _)| kind == "Battery");
+    let on_mains = supplies
+        .iter()
+        .any(|(kind, online)| kind == "Mains" && online == "1");
+    Check {
+        category: "power",
+        passed: !has_battery || on_mains,
+        detail: match (has_battery, on_mains) {
+            (false, _) => "no battery present".into(),
+            (true, true) => "on AC power".into(),
+            (true, false) => "running on battery; connect AC power".into(),
+        },
+    }
+}
+
+pub fn preflight_checks() -> Vec<Check> {
+    vec![
+        check_free_space("/", MIN_FREE_ROOT),
+        check_free_space("/var/cache", MIN_FREE_VAR_CACHE),
+        check_boot_capacity(),
+        check_locks(),
+        check_package_managers(),
+        check_power(),
+    ]
+}
+
+// Print and record the checks; Err(EXIT_PREFLIGHT) if any failed.
+pub(crate) fn run_preflight(report: &mut RunReport, opts: &Options) -> Result<(), i32> {
+    if opts.skip_preflight {
+        color_print!(
+            YELLOW,
+            "⚠️  Pre-flight checks skipped (--skip-preflight)\n\n"
+        );
+        return Ok(());
+    }
+    color_print!(format!("{BLUE}{BOLD}"), "➜ Pre-flight checks …\n");
+    report.preflight = preflight_checks();
+    for check in &report.preflight {
+        let (color, mark) = if check.passed {
+            (GREEN, "✅")
+        } else {
+            (RED, "❌")
+        };
+        color_print!(color, "  {} [{}]", mark, check.category);
+        color_print!("", " {}\n", check.detail);
+    }
+    color_print!("", "\n");
+    let failed: Vec<&str> = report
+        .preflight
+        .iter()
+        .filter(|c| !c.passed)
+        .map(|c| c.category)
+        .collect();
+    if !failed.is_empty() {
+        color_print!(
+            RED,
+            "❌ Pre-flight checks failed ({}); nothing was changed.\n",
+            failed.join(", ")
+        );
+        return Err(EXIT_PREFLIGHT);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+    use std::os::unix::fs::MetadataExt;
+
+    use super::*;
+
+    #[test]
+    fn running_flatpak_apps_are_not_package_managers() {
+        assert!(changes_packages("apt-get", ""));
+        assert!(changes_packages(
+            "flatpak",
+            "flatpak\0--user\0install\0-y\0org.gnome.Maps\0"
+        ));
+        assert!(changes_packages("flatpak", "/usr/bin/flatpak\0update\0"));
+        assert!(!changes_packages(
+            "flatpak",
+            "/usr/bin/flatpak\0run\0--branch=stable\0org.mozilla.firefox\0"
+        ));
+        assert!(!changes_packages("flatpak", "flatpak\0--version\0"));
+    }
+
+    #[test]
+    fn lock_holder_matches_the_lock_file_inode() {
+        let path = env::temp_dir().join(format!("rhino-update-lock-{}", std::process::id()));
+        fs::write(&path, "").unwrap();
+        let inode = fs::metadata(&path).unwrap().ino();
+        let path = path.to_str().unwrap();
+        // Waiters ("->") are not holders; other inodes are other files.
+        let locks = format!(
+            "1: POSIX  ADVISORY  WRITE 4242 fd:01:{} 0 EOF\n\
+             1: -> POSIX  ADVISORY  WRITE 5151 fd:01:{inode} 0 EOF\n\
+             2: POSIX  ADVISORY  WRITE 1717 fd:01:{inode} 0 EOF\n",
+            inode + 1
+        );
+        assert_eq!(lock_holder(path, &locks), Some(1717));
+        assert_eq!(
+            lock_holder(path, "1: FLOCK  ADVISORY  WRITE 9 fd:01:1 0 EOF\n"),
+            None
+        );
+        fs::remove_file(path).unwrap();
+        assert_eq!(lock_holder(path, &locks), None);
+    }
+}
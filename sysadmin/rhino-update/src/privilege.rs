@@ -0,0 +1,231 @@
+use std::env;
+use std::fs;
+use std::io::{self, IsTerminal};
+use std::os::unix::process::CommandExt;
+use std::process::Command;
+
+use crate::cli::Options;
+use crate::exec::{command_exists, runner};
+use crate::ui::{confirm, CYAN, RED, YELLOW};
+
+// Privileges ------------------------------------------------------------------
+// Changing packages takes euid 0 plus the capabilities dpkg and apt rely on,
+// which a container can drop even for root. Unprivileged, the rhino-update
+// command runs itself again through sudo (or pkexec without sudo) with the
+// same arguments and the environment that shapes its behaviour, after asking
+// on a terminal. Without a terminal only `sudo -n` is tried, so it never
+// hangs on a password; --no-elevate turns this off and the run just fails.
+const ELEVATED_MARKER: &str = "RHINO_UPDATE_ELEVATED";
+
+// sudo resets the environment; these survive into the elevated run.
+const PRESERVED_ENV: [&str; 11] = [
+    "TERM",
+    "COLORTERM",
+    "LANG",
+    "LC_ALL",
+    "LC_CTYPE",
+    "NO_COLOR",
+    "CLICOLOR_FORCE",
+    "http_proxy",
+    "https_proxy",
+    "no_proxy",
+    "RHINO_UPDATE_STATE_DIR",
+];
+
+// pkexec keeps only the terminal and locale variables and has no way to pass
+// others, so the colour ones become options and the rest are lost.
+const PKEXEC_KEPT_ENV: [&str; 5] = ["TERM", "COLORTERM", "LANG", "LC_ALL", "LC_CTYPE"];
+
+// Bits in /proc/self/status CapEff: file ownership and permission overrides
+// for dpkg, and switching to the _apt user for downloads.
+const REQUIRED_CAPS: [(u32, &str); 5] = [
+    (0, "CAP_CHOWN"),
+    (1, "CAP_DAC_OVERRIDE"),
+    (3, "CAP_FOWNER"),
+    (6, "CAP_SETGID"),
+    (7, "CAP_SETUID"),
+];
+
+extern "C" {
+    fn geteuid() -> u32;
+}
+
+pub fn is_root() -> bool {
+    // SAFETY: geteuid(2) cannot fail and has no preconditions.
+    unsafe { geteuid() == 0 }
+}
+
+pub(crate) fn missing_capabilities(status: &str) -> Vec<&'static str> {
+    let Some(effective) = status
+        .lines()
+        .find_map(|line| line.strip_prefix("CapEff:"))
+        .and_then(|hex| u64::from_str_radix(hex.trim(), 16).ok())
+    else {
+        return Vec::new();
+    };
+    REQUIRED_CAPS
+        .iter()
+        .filter(|(bit, _)| effective & (1 << bit) == 0)
+        .map(|(_, name)| *name)
+        .collect()
+}
+
+// `elevator` running `argv` (the program first) with the `preserved`
+// variables, e.g. "sudo -n --preserve-env=LANG,RHINO_UPDATE_ELEVATED --
+// /usr/bin/rhino-update --reboot=never". Sudoers rules and polkit see the
+// program itself rather than a wrapper such as env.
+pub(crate) fn elevation_command(
+    elevator: &str,
+    interactive: bool,
+    argv: &[String],
+    preserved: &[(String, String)],
+) -> Vec<String> {
+    let mut cmd = vec![elevator.to_string()];
+    let (program, args) = argv.split_first().expect("argv includes the program");
+    if elevator == "sudo" {
+        if !interactive {
+            cmd.push("-n".into());
+        }
+        let mut names: Vec<&str> = preserved.iter().map(|(key, _)| key.as_str()).collect();
+        names.push(ELEVATED_MARKER);
+        cmd.push(format!("--preserve-env={}", names.join(",")));
+        cmd.push("--".into());
+        cmd.push(program.clone());
+    } else {
+        cmd.push(program.clone());
+        let set = |key: &str| preserved.iter().any(|(k, v)| k == key && !v.is_empty());
+        // Before the original arguments, so an explicit --color still wins.
+        if set("NO_COLOR") {
+            cmd.push("--color=never".into());
+        } else if set("CLICOLOR_FORCE") {
+            cmd.push("--color=always".into());
+        }
+    }
+    cmd.extend(args.iter().cloned());
+    cmd
+}
+
+// Replace this process with `argv` run elevated. Only returns, with the
+// reason, when that was declined or could not be started.
+fn elevate(opts: &Options, argv: &[String]) -> String {
+    let interactive = !opts.non_interactive && io::stdin().is_terminal();
+    let candidates: &[&str] = if interactive {
+        &["sudo", "pkexec"]
+    } else {
+        &["sudo"]
+    };
+    let Some(elevator) = candidates.iter().copied().find(|e| command_exists(e)) else {
+        return format!("Cannot elevate: {} not found", candidates.join(" or "));
+    };
+    if interactive
+        && !confirm(
+            &format!("🔐 Root privileges are needed. Re-run with {elevator}?"),
+            true,
+        )
+    {
+        return "Elevation declined".into();
+    }
+    let preserved: Vec<(String, String)> = PRESERVED_ENV
+        .iter()
+        .filter_map(|key| Some((key.to_string(), env::var(key).ok()?)))
+        .collect();
+    if elevator == "pkexec" {
+        let lost: Vec<&str> = preserved
+            .iter()
+            .map(|(key, _)| key.as_str())
+            .filter(|key| !PKEXEC_KEPT_ENV.contains(key) && !key.contains("COLOR"))
+            .collect();
+        if !lost.is_empty() {
+            color_print!(
+                YELLOW,
+                "⚠️  pkexec does not pass on {}; use sudo to keep them\n",
+                lost.join(", ")
+            );
+        }
+    }
+    let cmd = elevation_command(elevator, interactive, argv, &preserved);
+    color_print!(CYAN, "🔐 Re-running with {}\n", elevator);
+    let err = Command::new(&cmd[0])
+        .args(&cmd[1..])
+        .env(ELEVATED_MARKER, "1")
+        .exec();
+    format!("Could not run {elevator}: {err}")
+}
+
+// Ok when the process may change packages. Otherwise re-run elevated when
+// opts.elevate_argv allows it, or report why not and return the exit code.
+pub fn require_root(opts: &Options) -> Result<(), i32> {
+    if !runner().is_root() {
+        // The markers stop a loop when sudo or pkexec did not make us root.
+        let elevated =
+            env::var_os(ELEVATED_MARKER).is_some() || env::var_os("PKEXEC_UID").is_some();
+        if let Some(argv) = opts.elevate_argv.as_deref() {
+            if !opts.no_elevate && !elevated && !argv.is_empty() {
+                color_print!(RED, "❌ {}\n", elevate(opts, argv));
+            }
+        }
+        color_print!(RED, "❌ This must be run as root (sudo rhino-update …).\n");
+        return Err(1);
+    }
+    // Capabilities only matter when the process itself is root rather than a
+    // runner standing in for it.
+    let status = fs::read_to_string("/proc/self/status").unwrap_or_default();
+    let missing = missing_capabilities(&status);
+    if is_root() && !missing.is_empty() {
+        color_print!(
+            RED,
+            "❌ Running as root without {} (a restricted container?); \
+             package managers would fail.\n",
+            missing.join(", ")
+        );
+        return Err(1);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn capabilities_come_from_cap_eff() {
+        let full = "Name:\trhino-update\nCapEff:\t000001ffffffffff\n";
+        assert!(missing_capabilities(full).is_empty());
+        // Docker's default set without CAP_CHOWN.
+        let dropped = "CapEff:\t00000000a80425fa\n";
+        assert_eq!(missing_capabilities(dropped), ["CAP_CHOWN"]);
+        assert_eq!(
+            missing_capabilities("CapEff:\t0000000000000000\n"),
+            REQUIRED_CAPS.map(|(_, name)| name)
+        );
+    }
+
+    #[test]
+    fn elevation_keeps_arguments_and_environment() {
+        let argv = [
+            "/usr/bin/rhino-update".to_string(),
+            "--reboot".to_string(),
+            "at 03:00".to_string(),
+        ];
+        let env = [
+            ("LANG".to_string(), "C.UTF-8".to_string()),
+            ("NO_COLOR".to_string(), "1".to_string()),
+        ];
+        assert_eq!(
+            elevation_command("sudo", false, &argv, &env),
+            [
+                "sudo",
+                "-n",
+                "--preserve-env=LANG,NO_COLOR,RHINO_UPDATE_ELEVATED",
+                "--",
+                "/usr/bin/rhino-update",
+                "--reboot",
+                "at 03:00"
+            ]
+        );
+        assert_eq!(
+            elevation_command("pkexec", true, &argv[..1], &env),
+            ["pkexec", "/usr/bin/rhino-update", "--color=never"]
+        );
+    }
+}
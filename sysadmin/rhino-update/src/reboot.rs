@@ -0,0 +1,237 @@
+use std::cmp::Ordering;
+use std::fs;
+use std::path::Path;
+
+use crate::backends::argv;
+use crate::cli::Options;
+use crate::exec::run;
+use crate::report::RunReport;
+use crate::ui::{CYAN, GREEN, YELLOW};
+
+// Reboot handling -------------------------------------------------------------
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub enum RebootPolicy {
+    #[default]
+    Never,
+    IfNeeded,
+    // "HH:MM", handed to shutdown(8).
+    At(String),
+}
+
+pub(crate) fn parse_reboot_time(time: &str) -> Result<String, String> {
+    let valid = time
+        .split_once(':')
+        .and_then(|(h, m)| Some((h.parse::<u8>().ok()?, m.parse::<u8>().ok()?, m.len())))
+        .is_some_and(|(h, m, len)| h < 24 && m < 60 && len == 2);
+    if valid {
+        Ok(time.to_string())
+    } else {
+        Err(format!("Invalid reboot time: {time} (expected HH:MM)"))
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub(crate) struct RebootStatus {
+    pub(crate) reasons: Vec<String>,
+    pub(crate) decision: String,
+}
+
+// Packages whose new version only takes effect after a reboot.
+pub(crate) const REBOOT_PACKAGES: [&str; 4] = [
+    "intel-microcode",
+    "amd64-microcode",
+    "libc6",
+    "linux-firmware",
+];
+
+// Compare versions the way dpkg does: epoch numerically, then the upstream
+// version and the revision (after the last '-'), each alternating non-digit
+// parts, where '~' sorts before anything and letters before other symbols,
+// with numeric parts (5.15.0-91 < 5.15.0-101, 1.0~rc1 < 1.0 < 1.0+b1).
+pub(crate) fn version_cmp(a: &str, b: &str) -> Ordering {
+    fn split(version: &str) -> (u64, &str, &str) {
+        let (epoch, rest) = match version.split_once(':') {
+            Some((epoch, rest)) if epoch.bytes().all(|b| b.is_ascii_digit()) => {
+                (epoch.parse().unwrap_or(0), rest)
+            }
+            _ => (0, version),
+        };
+        let (upstream, revision) = rest.rsplit_once('-').unwrap_or((rest, ""));
+        (epoch, upstream, revision)
+    }
+    let (a, b) = (split(a), split(b));
+    a.0.cmp(&b.0)
+        .then_with(|| dpkg_part_cmp(a.1.as_bytes(), b.1.as_bytes()))
+        .then_with(|| dpkg_part_cmp(a.2.as_bytes(), b.2.as_bytes()))
+}
+
+// dpkg's verrevcmp.
+fn dpkg_part_cmp(mut a: &[u8], mut b: &[u8]) -> Ordering {
+    fn order(c: Option<&u8>) -> i32 {
+        match c {
+            None => 0,
+            Some(c) if c.is_ascii_digit() => 0,
+            Some(b'~') => -1,
+            Some(c) if c.is_ascii_alphabetic() => i32::from(*c),
+            Some(c) => i32::from(*c) + 256,
+        }
+    }
+    let digit = |s: &[u8]| s.first().is_some_and(u8::is_ascii_digit);
+    while !a.is_empty() || !b.is_empty() {
+        while (!a.is_empty() && !digit(a)) || (!b.is_empty() && !digit(b)) {
+            let (x, y) = (order(a.first()), order(b.first()));
+            if x != y {
+                return x.cmp(&y);
+            }
+            a = &a[1..];
+            b = &b[1..];
+        }
+        while a.first() == Some(&b'0') {
+            a = &a[1..];
+        }
+        while b.first() == Some(&b'0') {
+            b = &b[1..];
+        }
+        let mut first_diff = Ordering::Equal;
+        while digit(a) && digit(b) {
+            first_diff = first_diff.then(a[0].cmp(&b[0]));
+            a = &a[1..];
+            b = &b[1..];
+        }
+        if digit(a) {
+            return Ordering::Greater;
+        }
+        if digit(b) {
+            return Ordering::Less;
+        }
+        if first_diff.is_ne() {
+            return first_diff;
+        }
+    }
+    Ordering::Equal
+}
+
+pub(crate) fn newest_installed_kernel() -> Option<String> {
+    fs::read_dir("/boot")
+        .ok()?
+        .filter_map(|entry| {
+            let name = entry.ok()?.file_name().into_string().ok()?;
+            name.strip_prefix("vmlinuz-").map(str::to_string)
+        })
+        .max_by(|a, b| version_cmp(a, b))
+}
+
+pub(crate) fn reboot_reasons(report: &RunReport) -> Vec<String> {
+    let mut reasons = Vec::new();
+    if Path::new("/var/run/reboot-required").exists() {
+        let pkgs = fs::read_to_string("/var/run/reboot-required.pkgs").unwrap_or_default();
+        let pkgs: Vec<&str> = pkgs.split_whitespace().collect();
+        reasons.push(if pkgs.is_empty() {
+            "/var/run/reboot-required is present".to_string()
+        } else {
+            format!("/var/run/reboot-required lists {}", pkgs.join(", "))
+        });
+    }
+    let running = fs::read_to_string("/proc/sys/kernel/osrelease")
+        .map(|r| r.trim().to_string())
+        .unwrap_or_default();
+    if let Some(newest) = newest_installed_kernel() {
+        if !running.is_empty() && version_cmp(&newest, &running).is_gt() {
+            reasons.push(format!(
+                "kernel {newest} is installed but {running} is running"
+            ));
+        }
+    }
+    for change in report.steps.iter().flat_map(|s| &s.packages) {
+        let base = change.name.split(':').next().unwrap_or_default();
+        if change.backend == "apt" && REBOOT_PACKAGES.contains(&base) {
+            reasons.push(format!("{base} was {}d", change.action.as_str()));
+        }
+    }
+    reasons
+}
+
+// Detect whether a reboot is needed and act on the policy.
+pub(crate) fn handle_reboot(report: &mut RunReport, opts: &Options) {
+    let reasons = reboot_reasons(report);
+    let decision = if reasons.is_empty() {
+        color_print!(GREEN, "✅ No reboot required\n");
+        "not-required".to_string()
+    } else {
+        color_print!(YELLOW, "\n🔁 Reboot required:\n");
+        for reason in &reasons {
+            color_print!("", "  • {}\n", reason);
+        }
+        let when = match &opts.reboot {
+            RebootPolicy::Never => None,
+            // A minute's grace lets this run finish writing its report.
+            RebootPolicy::IfNeeded => Some("+1".to_string()),
+            RebootPolicy::At(time) => Some(time.clone()),
+        };
+        match when {
+            None => {
+                color_print!(
+                    CYAN,
+                    "ℹ️  Not rebooting (--reboot=never); reboot when convenient\n"
+                );
+                "deferred".to_string()
+            }
+            Some(when) => {
+                let cmd = argv(&[
+                    "shutdown",
+                    "-r",
+                    &when,
+                    "rhino-update: reboot to finish upgrades",
+                ]);
+                let step = run(&cmd, "Scheduling reboot …", None);
+                let decision = if step.succeeded() {
+                    format!("scheduled {when}")
+                } else {
+                    "schedule-failed".to_string()
+                };
+                report.steps.push(step);
+                decision
+            }
+        }
+    };
+    report.reboot = Some(RebootStatus { reasons, decision });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn versions_compare_like_dpkg() {
+        let ordered = [
+            ("1.0~rc1", "1.0"),
+            ("1.0~~", "1.0~"),
+            ("1.0", "1.0+b1"),
+            ("1.0", "1.0a"),
+            ("1.0a", "1.0+"),
+            ("2.10-3", "2.10-3ubuntu1"),
+            ("2.10-3ubuntu1", "2.10-10"),
+            ("5.15.0-91", "5.15.0-101"),
+            ("9.9-1", "1:0.1-1"),
+            ("1:1.2.3", "2:0"),
+            ("1.2-1", "1.2.0-1"),
+            ("6.8.0-45-generic", "6.8.0-101-generic"),
+            ("CVE-2024-999", "CVE-2024-1000"),
+        ];
+        for (older, newer) in ordered {
+            assert_eq!(
+                version_cmp(older, newer),
+                Ordering::Less,
+                "{older} < {newer}"
+            );
+            assert_eq!(
+                version_cmp(newer, older),
+                Ordering::Greater,
+                "{newer} > {older}"
+            );
+        }
+        for (a, b) in [("1.0", "1.00"), ("0:1.0-1", "1.0-1"), ("1.0", "1.0-0")] {
+            assert_eq!(version_cmp(a, b), Ordering::Equal, "{a} = {b}");
+        }
+    }
+}
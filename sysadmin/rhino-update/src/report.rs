@@ -0,0 +1,299 @@
+use std::fs;
+use std::io::{self, Write};
+use std::time::SystemTime;
+
+use crate::backends::PackageChange;
+use crate::changelog::{CveFinding, PackageChangelog};
+use crate::cli::Options;
+use crate::exec::Step;
+use crate::holds::held_back_status;
+use crate::json::Json;
+use crate::log::{log, Level};
+use crate::preflight::Check;
+use crate::reboot::RebootStatus;
+use crate::services::StaleService;
+use crate::signals::{interrupt, interrupted};
+use crate::snapshot::Snapshot;
+use crate::store::save_run;
+use crate::time::{format_timestamp, hostname, new_run_id};
+use crate::ui::{RED, YELLOW};
+use crate::window::EXIT_DEFERRED;
+
+// Run report -----------------------------------------------------------------
+pub struct RunReport {
+    pub(crate) run_id: String,
+    pub(crate) host: String,
+    pub(crate) mode: &'static str,
+    pub(crate) started_at: SystemTime,
+    pub(crate) finished_at: SystemTime,
+    pub(crate) outcome: &'static str,
+    pub(crate) exit_code: i32,
+    pub(crate) rollback_of: Option<String>,
+    pub(crate) snapshot: Option<Snapshot>,
+    pub(crate) backends: Vec<(&'static str, bool)>,
+    pub(crate) steps: Vec<Step>,
+    pub(crate) planned: Vec<PackageChange>,
+    // Bytes downloaded by upgrades whose output does not say (flatpak).
+    pub(crate) downloaded: u64,
+    pub(crate) held_back: Vec<PackageChange>,
+    pub(crate) changelogs: Vec<PackageChangelog>,
+    pub(crate) cves: Vec<CveFinding>,
+    pub(crate) preflight: Vec<Check>,
+    pub(crate) stale_services: Vec<StaleService>,
+    pub(crate) stale_processes: Vec<String>,
+    pub(crate) reboot: Option<RebootStatus>,
+    pub(crate) warnings: Vec<String>,
+}
+
+impl RunReport {
+    pub fn new(mode: &'static str) -> Self {
+        let started_at = SystemTime::now();
+        RunReport {
+            run_id: new_run_id(started_at),
+            host: hostname(),
+            mode,
+            started_at,
+            finished_at: started_at,
+            outcome: "running",
+            exit_code: 0,
+            rollback_of: None,
+            snapshot: None,
+            backends: Vec::new(),
+            steps: Vec::new(),
+            planned: Vec::new(),
+            downloaded: 0,
+            held_back: Vec::new(),
+            changelogs: Vec::new(),
+            cves: Vec::new(),
+            preflight: Vec::new(),
+            stale_services: Vec::new(),
+            stale_processes: Vec::new(),
+            reboot: None,
+            warnings: Vec::new(),
+        }
+    }
+
+    pub fn finish(&mut self, outcome: &'static str, exit_code: i32) {
+        self.finished_at = SystemTime::now();
+        self.outcome = outcome;
+        self.exit_code = exit_code;
+        let level = match outcome {
+            "success" => Level::Info,
+            "failed" => Level::Error,
+            _ => Level::Warning,
+        };
+        log(
+            level,
+            &format!("{} run finished: {outcome}", self.mode),
+            &[("EXIT_CODE", exit_code.to_string())],
+        );
+    }
+
+    pub fn run_id(&self) -> &str {
+        &self.run_id
+    }
+
+    pub fn mode(&self) -> &'static str {
+        self.mode
+    }
+
+    // "running" until finished, then "success", "failed", "deferred" or
+    // "interrupted".
+    pub fn outcome(&self) -> &'static str {
+        self.outcome
+    }
+
+    pub fn exit_code(&self) -> i32 {
+        self.exit_code
+    }
+
+    pub fn steps(&self) -> &[Step] {
+        &self.steps
+    }
+
+    pub fn planned(&self) -> &[PackageChange] {
+        &self.planned
+    }
+
+    pub fn warnings(&self) -> &[String] {
+        &self.warnings
+    }
+
+    // Print a warning and keep it for the summary and the report.
+    pub(crate) fn warn(&mut self, message: String) {
+        color_print!(YELLOW, "⚠️  {}\n", message);
+        self.warnings.push(message);
+    }
+
+    // Per-backend result: unavailable, skipped (never reached), failed or success.
+    pub fn backend_status(&self, name: &str, available: bool) -> &'static str {
+        let mut steps = self.steps.iter().filter(|s| s.backend == Some(name));
+        if !available {
+            "unavailable"
+        } else if steps.clone().next().is_none() {
+            "skipped"
+        } else if steps.all(Step::succeeded) {
+            "success"
+        } else {
+            "failed"
+        }
+    }
+
+    pub fn to_json(&self) -> Json {
+        let backends = self
+            .backends
+            .iter()
+            .map(|&(name, available)| {
+                Json::obj([
+                    ("name", name.into()),
+                    ("status", self.backend_status(name, available).into()),
+                ])
+            })
+            .collect();
+        let duration = self
+            .finished_at
+            .duration_since(self.started_at)
+            .unwrap_or_default();
+        Json::obj([
+            ("run_id", self.run_id.as_str().into()),
+            ("host", self.host.as_str().into()),
+            ("mode", self.mode.into()),
+            ("started_at", format_timestamp(self.started_at).into()),
+            ("finished_at", format_timestamp(self.finished_at).into()),
+            ("duration_secs", Json::Num(duration.as_secs_f64())),
+            ("outcome", self.outcome.into()),
+            ("exit_code", self.exit_code.into()),
+            ("rollback_of", self.rollback_of.clone().into()),
+            (
+                "preflight",
+                Json::Arr(self.preflight.iter().map(Json::from).collect()),
+            ),
+            (
+                "snapshot",
+                self.snapshot.as_ref().map_or(Json::Null, |snap| {
+                    Json::obj([
+                        ("kind", snap.kind.into()),
+                        ("name", snap.name.as_str().into()),
+                    ])
+                }),
+            ),
+            ("backends", Json::Arr(backends)),
+            (
+                "steps",
+                Json::Arr(self.steps.iter().map(Json::from).collect()),
+            ),
+            (
+                "planned",
+                Json::Arr(self.planned.iter().map(Json::from).collect()),
+            ),
+            (
+                "held_back",
+                Json::Arr(
+                    self.held_back
+                        .iter()
+                        .map(|change| {
+                            let mut entry = Json::from(change);
+                            if let Json::Obj(fields) = &mut entry {
+                                fields.push(("status".into(), held_back_status(change).into()));
+                            }
+                            entry
+                        })
+                        .collect(),
+                ),
+            ),
+            (
+                "changelogs",
+                Json::Arr(self.changelogs.iter().map(Json::from).collect()),
+            ),
+            (
+                "cves",
+                Json::Arr(self.cves.iter().map(Json::from).collect()),
+            ),
+            (
+                "reboot",
+                self.reboot.as_ref().map_or(Json::Null, |reboot| {
+                    Json::obj([
+                        ("required", Json::Bool(!reboot.reasons.is_empty())),
+                        (
+                            "reasons",
+                            Json::Arr(reboot.reasons.iter().map(|r| r.as_str().into()).collect()),
+                        ),
+                        ("decision", reboot.decision.as_str().into()),
+                    ])
+                }),
+            ),
+            (
+                "stale_services",
+                Json::Arr(self.stale_services.iter().map(Json::from).collect()),
+            ),
+            (
+                "stale_processes",
+                Json::Arr(
+                    self.stale_processes
+                        .iter()
+                        .map(|p| p.as_str().into())
+                        .collect(),
+                ),
+            ),
+            (
+                "warnings",
+                Json::Arr(self.warnings.iter().map(|w| w.as_str().into()).collect()),
+            ),
+        ])
+    }
+}
+
+// Record the failure and emit the report; returns `code` to exit with.
+pub(crate) fn abort(report: &mut RunReport, opts: &Options, code: i32) -> i32 {
+    if let Some(signum) = interrupted() {
+        return interrupt(report, opts, signum);
+    }
+    report.finish("failed", code);
+    emit_report(report, opts);
+    code
+}
+
+pub(crate) fn defer(report: &mut RunReport, opts: &Options, reason: &str) -> i32 {
+    color_print!(
+        YELLOW,
+        "⏸  Deferred: {}; try again in the next maintenance window.\n",
+        reason
+    );
+    report.finish("deferred", EXIT_DEFERRED);
+    emit_report(report, opts);
+    EXIT_DEFERRED
+}
+
+// Persist the run, then emit the report if requested. Unprivileged dry-runs
+// cannot write the store, and that is not worth a warning.
+pub fn emit_report(report: &RunReport, opts: &Options) {
+    match save_run(report) {
+        Err(e) if report.mode == "dry-run" && e.kind() == io::ErrorKind::PermissionDenied => {}
+        Err(e) => color_print!(
+            YELLOW,
+            "⚠️  Could not record run {}: {}\n",
+            report.run_id,
+            e
+        ),
+        Ok(_) => {}
+    }
+    if opts.report {
+        write_report(&report.to_json(), opts);
+    }
+}
+
+// The JSON report goes to --report-file, or to stdout with the human-readable
+// output moved to stderr.
+pub(crate) fn write_report(report: &Json, opts: &Options) {
+    let json = format!("{report}\n");
+    match &opts.report_file {
+        Some(path) => {
+            if let Err(e) = fs::write(path, json) {
+                color_print!(RED, "❌ Could not write report to {}: {}\n", path, e);
+            }
+        }
+        None => {
+            let _ = io::stdout().write_all(json.as_bytes());
+        }
+    }
+}
@@ -0,0 +1,639 @@
+use std::fs;
+use std::io::{self, IsTerminal};
+use std::path::PathBuf;
+
+use crate::backends::{all_backends, argv, step_transactions, Action, Apt, PackageChange};
+use crate::cli::Options;
+use crate::exec::{capture, merge_changes, run, run_retrying, Step};
+use crate::json::{change_from_json, Json};
+use crate::log::{init_logging, LOG_FILE};
+use crate::preflight::run_preflight;
+use crate::privilege::require_root;
+use crate::report::{abort, emit_report, RunReport};
+use crate::signals::{install_signal_handlers, interrupt, interrupted, recover_after_timeout};
+use crate::snapshot::Snapshot;
+use crate::store::load_run;
+use crate::ui::{confirm, BOLD, CYAN, GREEN, MAGENTA, RED, YELLOW};
+
+// Rollback --------------------------------------------------------------------
+pub(crate) const APT_ARCHIVES: &str = "/var/cache/apt/archives";
+
+// Net effect of a run per package: first old version, last new version.
+// pacstall's builds are dpkg packages, so the inventory lists them under apt;
+// the steps' transactions say which of them are pacstall's.
+pub fn net_changes(steps: &[Step]) -> Vec<PackageChange> {
+    net_effect(steps.iter().map(|s| (&s.packages[..], &s.transactions[..])))
+}
+
+// The same for a run loaded from its record.
+pub fn recorded_net_changes(run: &Json) -> Vec<PackageChange> {
+    let changes = |step: &Json, key: &str| -> Vec<PackageChange> {
+        step.get(key)
+            .as_arr()
+            .iter()
+            .filter_map(change_from_json)
+            .collect()
+    };
+    let steps: Vec<(Vec<PackageChange>, Vec<PackageChange>)> = run
+        .get("steps")
+        .as_arr()
+        .iter()
+        .map(|step| (changes(step, "packages"), changes(step, "transactions")))
+        .collect();
+    net_effect(steps.iter().map(|(p, t)| (&p[..], &t[..])))
+}
+
+// Steps as (inventory diff, parsed transactions).
+fn net_effect<'a, I>(steps: I) -> Vec<PackageChange>
+where
+    I: Iterator<Item = (&'a [PackageChange], &'a [PackageChange])> + Clone,
+{
+    let pacstall: Vec<&str> = steps
+        .clone()
+        .flat_map(|(_, transactions)| transactions)
+        .filter(|change| change.backend == "pacstall")
+        .map(|change| change.name.as_str())
+        .collect();
+    let mut net: Vec<PackageChange> = Vec::new();
+    for (packages, _) in steps {
+        let mut packages = packages.to_vec();
+        for change in &mut packages {
+            if change.backend == "apt" && pacstall.contains(&change.name.as_str()) {
+                change.backend = "pacstall";
+            }
+        }
+        merge_changes(&mut net, packages);
+    }
+    net.sort_by(|a, b| (a.backend, &a.name).cmp(&(b.backend, &b.name)));
+    net
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct RollbackPlan {
+    // Arguments for one `apt-get install` transaction ("pkg=ver", "./x.deb", "pkg-").
+    pub(crate) apt_targets: Vec<String>,
+    pub(crate) commands: Vec<(&'static str, Vec<String>)>,
+    // Changes that cannot be reverted package by package, such as pacstall's,
+    // which keeps no previous builds.
+    pub(crate) unresolved: Vec<PackageChange>,
+}
+
+pub(crate) fn plan_rollback(changes: &[PackageChange]) -> RollbackPlan {
+    let mut plan = RollbackPlan::default();
+    for change in changes {
+        let name = change.name.as_str();
+        // Whether the run left the package installed, i.e. did not remove it.
+        let present = change.new_version.is_some();
+        let flatpak = |subcommand: &str, args: &[&str]| {
+            let mut cmd = argv(&["flatpak", subcommand, "-y", "--noninteractive"]);
+            cmd.extend(argv(args));
+            ("flatpak", cmd)
+        };
+        match (change.backend, change.old_version.as_deref()) {
+            ("apt", Some(version)) => match apt_restore_target(name, version) {
+                Some(target) => plan.apt_targets.push(target),
+                None => plan.unresolved.push(change.clone()),
+            },
+            ("apt", None) => plan.apt_targets.push(format!("{name}-")),
+            ("flatpak", Some(version)) => {
+                let commit = flatpak_commit(version).map(|c| format!("--commit={c}"));
+                match (commit, flatpak_origin(version)) {
+                    (Some(commit), _) if present => {
+                        plan.commands.push(flatpak("update", &[&commit, name]))
+                    }
+                    (Some(commit), Some(origin)) => {
+                        plan.commands.push(flatpak("install", &[origin, name]));
+                        plan.commands.push(flatpak("update", &[&commit, name]));
+                    }
+                    _ => plan.unresolved.push(change.clone()),
+                }
+            }
+            ("flatpak", None) => plan.commands.push(flatpak("uninstall", &[name])),
+            ("snap", Some(version)) => match snap_revision(version) {
+                Some(rev) if present => plan.commands.push((
+                    "snap",
+                    argv(&["snap", "revert", &format!("--revision={rev}"), name]),
+                )),
+                None if present => plan
+                    .commands
+                    .push(("snap", argv(&["snap", "revert", name]))),
+                Some(rev) => plan.commands.push((
+                    "snap",
+                    argv(&["snap", "install", &format!("--revision={rev}"), name]),
+                )),
+                None => plan.unresolved.push(change.clone()),
+            },
+            ("snap", None) => plan
+                .commands
+                .push(("snap", argv(&["snap", "remove", name]))),
+            _ => plan.unresolved.push(change.clone()),
+        }
+    }
+    plan
+}
+
+// Prefer the .deb still in apt's archive cache, then any configured repository.
+pub(crate) fn apt_restore_target(name: &str, version: &str) -> Option<String> {
+    if let Some(deb) = cached_deb(name, version) {
+        return Some(deb.display().to_string());
+    }
+    let target = format!("{name}={version}");
+    // apt-cache exits 0 with empty output for unknown versions.
+    capture(&["apt-cache", "show", &target])
+        .filter(|out| !out.trim().is_empty())
+        .map(|_| target)
+}
+
+pub(crate) fn cached_deb(name: &str, version: &str) -> Option<PathBuf> {
+    let (base, arch) = name.split_once(':').unwrap_or((name, ""));
+    let prefix = format!("{base}_{}_", version.replace(':', "%3a"));
+    fs::read_dir(APT_ARCHIVES)
+        .ok()?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .find(|path| {
+            path.file_name().and_then(|n| n.to_str()).is_some_and(|n| {
+                n.starts_with(&prefix)
+                    && n.ends_with(".deb")
+                    && (arch.is_empty()
+                        || n.ends_with(&format!("_{arch}.deb"))
+                        || n.ends_with("_all.deb"))
+            })
+        })
+}
+
+// Flatpak versions are recorded as "1.4.2 (flathub <commit>)", or without
+// the remote by older runs, and snap versions as "120.0-2 (4356)".
+pub(crate) fn version_detail(version: &str) -> Option<&str> {
+    let detail = version.rsplit_once('(')?.1.strip_suffix(')')?.trim();
+    (!detail.is_empty()).then_some(detail)
+}
+
+pub(crate) fn flatpak_commit(version: &str) -> Option<&str> {
+    version_detail(version)?.split_whitespace().last()
+}
+
+pub(crate) fn flatpak_origin(version: &str) -> Option<&str> {
+    let (origin, _commit) = version_detail(version)?.split_once(' ')?;
+    Some(origin)
+}
+
+pub(crate) fn snap_revision(version: &str) -> Option<&str> {
+    version_detail(version).filter(|rev| rev.bytes().all(|b| b.is_ascii_digit()))
+}
+
+pub(crate) fn snapshot_from_json(value: &Json) -> Option<Snapshot> {
+    let kind = match value.get("kind").as_str()? {
+        "btrfs" => "btrfs",
+        "timeshift" => "timeshift",
+        _ => return None,
+    };
+    let name = value.get("name").as_str()?.to_string();
+    Some(Snapshot { kind, name })
+}
+
+// Btrfs restores only work when the root subvolume is not pinned in fstab;
+// otherwise set-default has no effect on the next boot.
+pub(crate) fn root_subvol_pinned() -> bool {
+    fs::read_to_string("/etc/fstab")
+        .unwrap_or_default()
+        .lines()
+        .filter(|line| !line.trim_start().starts_with('#'))
+        .any(|line| {
+            let cols: Vec<&str> = line.split_whitespace().collect();
+            cols.len() > 3 && cols[1] == "/" && cols[3].split(',').any(|o| o.starts_with("subvol"))
+        })
+}
+
+pub(crate) fn restore_snapshot(snapshot: &Snapshot) -> Vec<Step> {
+    match snapshot.kind {
+        "btrfs" => {
+            if root_subvol_pinned() {
+                color_print!(
+                    RED,
+                    "❌ / is mounted with an explicit subvol= option; restore {} manually.\n",
+                    snapshot.name
+                );
+                return Vec::new();
+            }
+            let restored = format!("{}-restored", snapshot.name);
+            let copy = run(
+                &argv(&["btrfs", "subvolume", "snapshot", &snapshot.name, &restored]),
+                "Creating a writable copy of the snapshot …",
+                None,
+            );
+            if !copy.succeeded() {
+                return vec![copy];
+            }
+            let default = run(
+                &argv(&["btrfs", "subvolume", "set-default", &restored]),
+                "Booting from the restored snapshot next time …",
+                None,
+            );
+            vec![copy, default]
+        }
+        _ => vec![run(
+            &argv(&[
+                "timeshift",
+                "--restore",
+                "--snapshot",
+                &snapshot.name,
+                "--scripted",
+                "--yes",
+                "--skip-grub",
+            ]),
+            "Restoring Timeshift snapshot …",
+            None,
+        )],
+    }
+}
+
+// The returned run report's exit code is the command's.
+pub fn cmd_rollback(opts: &Options, run_id: Option<&str>) -> RunReport {
+    let mut report = RunReport::new("rollback");
+    rollback(&mut report, opts, run_id);
+    report
+}
+
+fn rollback(report: &mut RunReport, opts: &Options, run_id: Option<&str>) -> i32 {
+    let record = match load_run(run_id, Some("update")) {
+        Ok(record) => record,
+        Err(msg) => {
+            color_print!(RED, "❌ Cannot load run: {}\n", msg);
+            report.finish("failed", 1);
+            return 1;
+        }
+    };
+    let target = record.get("run_id").as_str().unwrap_or("?").to_string();
+    let snapshot = snapshot_from_json(record.get("snapshot"));
+    let changes = recorded_net_changes(&record);
+    let plan = plan_rollback(&changes);
+
+    color_print!(
+        format!("{MAGENTA}{BOLD}"),
+        "🦏 Rolling back run {}{}\n\n",
+        target,
+        if opts.dry_run { " (dry-run)" } else { "" }
+    );
+    for change in &changes {
+        let from = change.new_version.as_deref().unwrap_or("absent");
+        let to = change.old_version.as_deref().unwrap_or("absent");
+        let color = if plan.unresolved.iter().any(|u| u.name == change.name) {
+            YELLOW
+        } else {
+            CYAN
+        };
+        color_print!(color, "  {:<8}", change.backend);
+        color_print!("", " {} {} → {}\n", change.name, from, to);
+    }
+    if changes.is_empty() {
+        color_print!(GREEN, "  no package changes were recorded for this run\n");
+    }
+    if !plan.unresolved.is_empty() {
+        color_print!(
+            YELLOW,
+            "⚠️  {} package(s) have no installable previous version{}\n",
+            plan.unresolved.len(),
+            match &snapshot {
+                Some(snap) if opts.restore_snapshot => {
+                    format!("; snapshot {} will be restored afterwards", snap.name)
+                }
+                _ => "; the others are still reverted".to_string(),
+            }
+        );
+    }
+
+    report.rollback_of = Some(target);
+    report.planned = changes.iter().map(reverse_change).collect();
+    if opts.dry_run {
+        report.mode = "dry-run";
+        report.finish("success", 0);
+        emit_report(report, opts);
+        return 0;
+    }
+    if let Err(code) = require_root(opts) {
+        report.finish("failed", code);
+        return code;
+    }
+    init_logging(opts.log_file.as_deref().unwrap_or(LOG_FILE), report);
+    install_signal_handlers();
+    if let Err(code) = run_preflight(report, opts) {
+        return abort(report, opts, code);
+    }
+
+    // Package by package first, as far as possible. Restoring the snapshot
+    // replaces the whole system, so it takes --restore-snapshot or a yes.
+    let mut ok = plan.unresolved.is_empty();
+    if !plan.apt_targets.is_empty() {
+        let mut cmd = argv(&[
+            "apt-get",
+            "install",
+            "-y",
+            "--allow-downgrades",
+            "-o",
+            "Dpkg::Options::=--force-confold",
+        ]);
+        cmd.extend(plan.apt_targets.iter().cloned());
+        let mut step = run_retrying(&cmd, "Restoring previous apt package versions …", None);
+        step.backend = Some("apt");
+        step.transactions = step_transactions(&Apt, &step);
+        ok &= step.succeeded();
+        let timed_out = step.timed_out;
+        report.steps.push(step);
+        if timed_out {
+            recover_after_timeout(report);
+        }
+    }
+    for (backend, cmd) in &plan.commands {
+        if interrupted().is_some() {
+            break;
+        }
+        let mut step = run_retrying(
+            cmd,
+            &format!("Reverting {} …", cmd.last().map_or("", |s| s)),
+            None,
+        );
+        step.backend = Some(backend);
+        if let Some(b) = all_backends().iter().find(|b| b.name() == *backend) {
+            step.transactions = step_transactions(b.as_ref(), &step);
+        }
+        ok &= step.succeeded();
+        report.steps.push(step);
+    }
+    if let Some(signum) = interrupted() {
+        return interrupt(report, opts, signum);
+    }
+    if !ok {
+        let interactive = !opts.non_interactive && io::stdin().is_terminal();
+        let snapshot_name = snapshot.as_ref().map(|snap| snap.name.clone());
+        let restore = snapshot.as_ref().is_some_and(|snap| {
+            opts.restore_snapshot
+                || interactive
+                    && confirm(
+                        &format!(
+                            "⚠️  Rollback incomplete. Restore snapshot {} over the whole system?",
+                            snap.name
+                        ),
+                        false,
+                    )
+        });
+        let Some(snap) = snapshot.filter(|_| restore) else {
+            color_print!(
+                RED,
+                "❌ Rollback of run {} incomplete.\n",
+                report.rollback_of.as_deref().unwrap_or("?")
+            );
+            let left: Vec<&str> = plan.unresolved.iter().map(|c| c.name.as_str()).collect();
+            if !left.is_empty() {
+                color_print!(YELLOW, "   Not reverted: {}\n", left.join(", "));
+            }
+            for step in report.steps.iter().filter(|s| !s.succeeded()) {
+                color_print!(YELLOW, "   Failed: {}\n", step.argv.join(" "));
+            }
+            if let Some(snap) = &snapshot_name {
+                color_print!(
+                    YELLOW,
+                    "   Snapshot {} holds the system from before the run; \
+                     rerun with --restore-snapshot to restore it.\n",
+                    snap
+                );
+            }
+            return abort(report, opts, 1);
+        };
+        let steps = restore_snapshot(&snap);
+        let restored = !steps.is_empty() && steps.iter().all(Step::succeeded);
+        let code = steps
+            .iter()
+            .find(|s| !s.succeeded())
+            .map_or(1, Step::failure_code);
+        report.steps.extend(steps);
+        report.snapshot = Some(snap);
+        if !restored {
+            return abort(report, opts, code);
+        }
+        color_print!(
+            YELLOW,
+            "⚠️  Snapshot restored; reboot to boot into the previous system state.\n"
+        );
+    }
+    color_print!(GREEN, "✅ Rollback complete.\n");
+    report.finish("success", 0);
+    emit_report(report, opts);
+    0
+}
+
+// The change a rollback makes to undo `change`.
+pub(crate) fn reverse_change(change: &PackageChange) -> PackageChange {
+    let action = match (&change.old_version, &change.new_version) {
+        (Some(_), Some(_)) => Action::Upgrade,
+        (Some(_), None) => Action::Install,
+        _ => Action::Remove,
+    };
+    PackageChange {
+        backend: change.backend,
+        name: change.name.clone(),
+        action,
+        old_version: change.new_version.clone(),
+        new_version: change.old_version.clone(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, SystemTime};
+
+    use super::*;
+    use crate::exec::testing::{install_fake, FakeRunner};
+
+    fn change(
+        backend: &'static str,
+        name: &str,
+        old: Option<&str>,
+        new: Option<&str>,
+    ) -> PackageChange {
+        PackageChange {
+            backend,
+            name: name.to_string(),
+            action: match (old, new) {
+                (Some(_), Some(_)) => Action::Upgrade,
+                (None, _) => Action::Install,
+                (_, None) => Action::Remove,
+            },
+            old_version: old.map(str::to_string),
+            new_version: new.map(str::to_string),
+        }
+    }
+
+    fn step(packages: Vec<PackageChange>, transactions: Vec<PackageChange>) -> Step {
+        let mut step = Step::new(&argv(&["true"]), "", SystemTime::now(), Duration::ZERO);
+        step.packages = packages;
+        step.transactions = transactions;
+        step
+    }
+
+    #[test]
+    fn net_changes_span_steps_and_attribute_pacstall_builds() {
+        let steps = [
+            step(
+                vec![
+                    change("apt", "hello", Some("2.10-3"), Some("2.10-4")),
+                    change("apt", "libnew1", None, Some("1.0-1")),
+                    change("apt", "oldlib1", Some("0.9-2"), None),
+                    change("apt", "flip", Some("1.0"), Some("1.1")),
+                    change("apt", "tmpdep", None, Some("1")),
+                    change("apt", "swap", Some("2.0"), None),
+                ],
+                Vec::new(),
+            ),
+            step(
+                vec![
+                    change("apt", "hello", Some("2.10-4"), Some("2.10-5")),
+                    change("apt", "flip", Some("1.1"), Some("1.0")),
+                    change("apt", "neofetch", Some("7.1.0"), Some("7.2.0")),
+                    change("apt", "tmpdep", Some("1"), None),
+                    change("apt", "swap", None, Some("2.1")),
+                ],
+                vec![change("pacstall", "neofetch", None, Some("7.2.0"))],
+            ),
+        ];
+        let net = |changes: Vec<PackageChange>| -> Vec<_> {
+            changes
+                .into_iter()
+                .map(|c| (c.backend, c.name, c.action, c.old_version, c.new_version))
+                .collect()
+        };
+        let some = |v: &str| Some(v.to_string());
+        assert_eq!(
+            net(net_changes(&steps)),
+            [
+                (
+                    "apt",
+                    "hello".into(),
+                    Action::Upgrade,
+                    some("2.10-3"),
+                    some("2.10-5")
+                ),
+                (
+                    "apt",
+                    "libnew1".into(),
+                    Action::Install,
+                    None,
+                    some("1.0-1")
+                ),
+                ("apt", "oldlib1".into(), Action::Remove, some("0.9-2"), None),
+                // Removed and installed again: an upgrade, as in the report.
+                (
+                    "apt",
+                    "swap".into(),
+                    Action::Upgrade,
+                    some("2.0"),
+                    some("2.1")
+                ),
+                (
+                    "pacstall",
+                    "neofetch".into(),
+                    Action::Upgrade,
+                    some("7.1.0"),
+                    some("7.2.0")
+                ),
+            ]
+        );
+        // A rollback reads the same from the run's record.
+        let record = Json::obj([("steps", Json::Arr(steps.iter().map(Json::from).collect()))]);
+        assert_eq!(net(recorded_net_changes(&record)), net(net_changes(&steps)));
+    }
+
+    #[test]
+    fn rollback_plans_undo_upgrades_installs_and_removals() {
+        install_fake(
+            FakeRunner::default()
+                .on(&["apt-cache", "show", "gone=0.1"], &[(0, "")])
+                .on(&["apt-cache", "show"], &[(0, "Package: hello\n")]),
+        );
+        let flatpak_old = "1.4.2 (flathub 3f2a)";
+        let cases = [
+            (
+                change("apt", "hello", Some("2.10-3"), Some("2.10-4")),
+                "apt-get install hello=2.10-3",
+            ),
+            (
+                change("apt", "libnew1", None, Some("1.0-1")),
+                "apt-get install libnew1-",
+            ),
+            (
+                change("apt", "oldlib1", Some("0.9-2"), None),
+                "apt-get install oldlib1=0.9-2",
+            ),
+            (
+                change("apt", "gone", Some("0.1"), Some("0.2")),
+                "unresolved gone",
+            ),
+            (
+                change(
+                    "flatpak",
+                    "org.gnome.Maps",
+                    Some(flatpak_old),
+                    Some("1.5 (flathub 9c1d)"),
+                ),
+                "flatpak update -y --noninteractive --commit=3f2a org.gnome.Maps",
+            ),
+            (
+                change("flatpak", "org.gnome.Maps", None, Some(flatpak_old)),
+                "flatpak uninstall -y --noninteractive org.gnome.Maps",
+            ),
+            (
+                change("flatpak", "org.gnome.Maps", Some(flatpak_old), None),
+                "flatpak install -y --noninteractive flathub org.gnome.Maps; \
+                 flatpak update -y --noninteractive --commit=3f2a org.gnome.Maps",
+            ),
+            // Recorded before versions carried the remote.
+            (
+                change("flatpak", "org.gnome.Maps", Some("1.4.2 (3f2a)"), None),
+                "unresolved org.gnome.Maps",
+            ),
+            (
+                change(
+                    "snap",
+                    "firefox",
+                    Some("120.0-2 (4356)"),
+                    Some("121.0-1 (4407)"),
+                ),
+                "snap revert --revision=4356 firefox",
+            ),
+            (
+                change("snap", "firefox", None, Some("121.0-1 (4407)")),
+                "snap remove firefox",
+            ),
+            (
+                change("snap", "firefox", Some("120.0-2 (4356)"), None),
+                "snap install --revision=4356 firefox",
+            ),
+            (
+                change("pacstall", "neofetch", Some("7.1.0"), Some("7.2.0")),
+                "unresolved neofetch",
+            ),
+            (
+                change("pacstall", "neofetch", None, Some("7.2.0")),
+                "unresolved neofetch",
+            ),
+            (
+                change("pacstall", "neofetch", Some("7.1.0"), None),
+                "unresolved neofetch",
+            ),
+        ];
+        for (change, expected) in cases {
+            let plan = plan_rollback(std::slice::from_ref(&change));
+            let planned: Vec<String> = plan
+                .apt_targets
+                .iter()
+                .map(|target| format!("apt-get install {target}"))
+                .chain(plan.commands.iter().map(|(_, cmd)| cmd.join(" ")))
+                .chain(
+                    plan.unresolved
+                        .iter()
+                        .map(|c| format!("unresolved {}", c.name)),
+                )
+                .collect();
+            assert_eq!(planned.join("; "), expected, "{change:?}");
+        }
+    }
+}
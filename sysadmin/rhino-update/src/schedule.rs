@@ -0,0 +1,211 @@
+use std::env;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::backends::argv;
+use crate::cli::Options;
+use crate::exec::{capture, command_exists, run};
+use crate::privilege::require_root;
+use crate::ui::{GREEN, RED, YELLOW};
+use crate::window::EXIT_DEFERRED;
+
+// systemd timer ---------------------------------------------------------------
+pub(crate) const SERVICE_UNIT: &str = "/etc/systemd/system/rhino-update.service";
+pub(crate) const TIMER_UNIT: &str = "/etc/systemd/system/rhino-update.timer";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ScheduleAction {
+    Install,
+    Status,
+    Remove,
+}
+
+// dpkg maintainer scripts need to write almost anywhere, create setuid files,
+// run sysctl and start namespaced helpers, so ProtectSystem, RestrictSUIDSGID,
+// ProtectKernelTunables and RestrictNamespaces are deliberately left out.
+pub(crate) fn service_unit(exec_start: &str) -> String {
+    format!(
+        "\
+# Generated by `rhino-update schedule install`; remove with `rhino-update schedule remove`.
+[Unit]
+Description=Unattended Rhino Linux update (rhino-update)
+Wants=network-online.target
+After=network-online.target
+ConditionACPower=true
+
+[Service]
+Type=oneshot
+ExecStart={exec_start}
+# Outside a maintenance window the run is deferred, not failed.
+SuccessExitStatus={EXIT_DEFERRED}
+Environment=DEBIAN_FRONTEND=noninteractive
+Nice=10
+CPUSchedulingPolicy=batch
+IOSchedulingClass=idle
+TimeoutStartSec=4h
+# Never SIGKILL a half-finished dpkg run on stop.
+KillMode=process
+PrivateTmp=true
+ProtectHome=read-only
+ProtectControlGroups=true
+ProtectHostname=true
+ProtectClock=true
+RestrictRealtime=true
+LockPersonality=true
+KeyringMode=private
+UMask=0022
+"
+    )
+}
+
+pub(crate) fn timer_unit(calendar: &str, randomized_delay: &str) -> String {
+    format!(
+        "\
+# Generated by `rhino-update schedule install`; remove with `rhino-update schedule remove`.
+[Unit]
+Description=Scheduled rhino-update run
+
+[Timer]
+OnCalendar={calendar}
+RandomizedDelaySec={randomized_delay}
+Persistent=true
+AccuracySec=1min
+
+[Install]
+WantedBy=timers.target
+"
+    )
+}
+
+// Quote an ExecStart argument when it contains anything beyond plain words.
+pub(crate) fn systemd_quote(arg: &str) -> String {
+    if !arg.is_empty()
+        && arg
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "/._-=:,@+".contains(c))
+    {
+        arg.to_string()
+    } else {
+        format!("\"{}\"", arg.replace('\\', "\\\\").replace('"', "\\\""))
+    }
+}
+
+pub(crate) fn schedule_install(opts: &Options) -> Result<(), String> {
+    let calendar = opts.calendar.as_deref().unwrap_or("Sun 03:00");
+    let delay = opts.randomized_delay.as_deref().unwrap_or("1h");
+    if command_exists("systemd-analyze")
+        && capture(&["systemd-analyze", "calendar", calendar]).is_none()
+    {
+        return Err(format!(
+            "systemd does not understand --calendar \"{calendar}\""
+        ));
+    }
+    let exe = env::current_exe().map_err(|e| format!("cannot locate rhino-update: {e}"))?;
+    let mut exec = vec![exe.display().to_string(), "--non-interactive".to_string()];
+    if let Some(config) = &opts.config {
+        exec.extend(["--config".to_string(), config.clone()]);
+    }
+    if let Some(profile) = &opts.profile {
+        exec.extend(["--profile".to_string(), profile.clone()]);
+    }
+    let exec_start: Vec<String> = exec.iter().map(|a| systemd_quote(a)).collect();
+
+    for (path, contents) in [
+        (SERVICE_UNIT, service_unit(&exec_start.join(" "))),
+        (TIMER_UNIT, timer_unit(calendar, delay)),
+    ] {
+        fs::write(path, contents).map_err(|e| format!("{path}: {e}"))?;
+        color_print!(GREEN, "✅ Wrote {}\n", path);
+    }
+    for cmd in [
+        argv(&["systemctl", "daemon-reload"]),
+        argv(&["systemctl", "enable", "--now", "rhino-update.timer"]),
+    ] {
+        let step = run(&cmd, "", None);
+        if !step.succeeded() {
+            return Err(format!("{} failed", cmd.join(" ")));
+        }
+    }
+    color_print!(
+        GREEN,
+        "✅ rhino-update will run {} (randomized delay up to {})\n",
+        calendar,
+        delay
+    );
+    Ok(())
+}
+
+pub(crate) fn schedule_status() -> Result<(), String> {
+    for path in [SERVICE_UNIT, TIMER_UNIT] {
+        if Path::new(path).exists() {
+            color_print!(GREEN, "✅ {} installed\n", path);
+        } else {
+            color_print!(YELLOW, "⚠️  {} not installed\n", path);
+        }
+    }
+    if Path::new(TIMER_UNIT).exists() {
+        run(
+            &argv(&[
+                "systemctl",
+                "list-timers",
+                "rhino-update.timer",
+                "--all",
+                "--no-pager",
+            ]),
+            "",
+            None,
+        );
+        run(
+            &argv(&[
+                "systemctl",
+                "status",
+                "rhino-update.service",
+                "--no-pager",
+                "--lines=5",
+            ]),
+            "",
+            None,
+        );
+    }
+    Ok(())
+}
+
+pub(crate) fn schedule_remove() -> Result<(), String> {
+    if Path::new(TIMER_UNIT).exists() {
+        run(
+            &argv(&["systemctl", "disable", "--now", "rhino-update.timer"]),
+            "",
+            None,
+        );
+    }
+    for path in [TIMER_UNIT, SERVICE_UNIT] {
+        match fs::remove_file(path) {
+            Ok(()) => color_print!(GREEN, "✅ Removed {}\n", path),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+            Err(e) => return Err(format!("{path}: {e}")),
+        }
+    }
+    run(&argv(&["systemctl", "daemon-reload"]), "", None);
+    Ok(())
+}
+
+pub fn cmd_schedule(opts: &Options, action: ScheduleAction) -> i32 {
+    if action != ScheduleAction::Status {
+        if let Err(code) = require_root(opts) {
+            return code;
+        }
+    }
+    let result = match action {
+        ScheduleAction::Status => schedule_status(),
+        ScheduleAction::Install => schedule_install(opts),
+        ScheduleAction::Remove => schedule_remove(),
+    };
+    match result {
+        Ok(()) => 0,
+        Err(msg) => {
+            color_print!(RED, "❌ {}\n", msg);
+            1
+        }
+    }
+}
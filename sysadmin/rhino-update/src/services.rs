@@ -0,0 +1,230 @@
+use std::collections::BTreeMap;
+use std::fs;
+
+use crate::backends::argv;
+use crate::cli::Options;
+use crate::exec::run;
+use crate::json::Json;
+use crate::preflight::process_name;
+use crate::report::RunReport;
+use crate::ui::{CYAN, GREEN, YELLOW};
+
+// Services needing a restart -------------------------------------------------
+// Mapped files outside these trees (shared memory, memfd, /tmp, …) are
+// routinely deleted while in use and say nothing about upgrades.
+pub(crate) const SYSTEM_PREFIXES: [&str; 6] =
+    ["/usr/", "/lib", "/bin/", "/sbin/", "/opt/", "/etc/"];
+
+// Restarting these would end sessions or wedge the system; report them only.
+pub(crate) const UNSAFE_UNITS: [&str; 9] = [
+    "dbus.service",
+    "dbus-broker.service",
+    "systemd-logind.service",
+    "display-manager.service",
+    "gdm.service",
+    "gdm3.service",
+    "sddm.service",
+    "lightdm.service",
+    "user@",
+];
+
+#[derive(Clone, Debug)]
+pub(crate) struct StaleService {
+    pub(crate) unit: String,
+    // "name (pid)" of each process still using replaced files.
+    pub(crate) processes: Vec<String>,
+    // pending, restarted, failed or skipped (unsafe to restart automatically).
+    pub(crate) status: &'static str,
+}
+
+impl From<&StaleService> for Json {
+    fn from(service: &StaleService) -> Json {
+        Json::obj([
+            ("unit", service.unit.as_str().into()),
+            (
+                "processes",
+                Json::Arr(
+                    service
+                        .processes
+                        .iter()
+                        .map(|p| p.as_str().into())
+                        .collect(),
+                ),
+            ),
+            ("status", service.status.into()),
+        ])
+    }
+}
+
+pub(crate) fn is_replaced(path: &str) -> bool {
+    path.ends_with(" (deleted)") && SYSTEM_PREFIXES.iter().any(|p| path.starts_with(p))
+}
+
+// Whether the process runs a deleted binary or maps deleted system libraries.
+pub(crate) fn uses_replaced_files(pid: u32) -> bool {
+    let exe = fs::read_link(format!("/proc/{pid}/exe"))
+        .map(|p| p.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    if is_replaced(&exe) {
+        return true;
+    }
+    fs::read_to_string(format!("/proc/{pid}/maps"))
+        .unwrap_or_default()
+        .lines()
+        // The pathname is the only field that can contain a '/'.
+        .any(|line| line.find('/').is_some_and(|i| is_replaced(&line[i..])))
+}
+
+// The system manager's service a process belongs to, from its cgroup path:
+// the outermost service, so anything a user's own manager runs counts as
+// that session's user@UID.service, which is never restarted.
+pub(crate) fn cgroup_unit(path: &str) -> Option<String> {
+    path.split('/')
+        .find(|part| part.ends_with(".service"))
+        .map(str::to_string)
+}
+
+pub(crate) fn systemd_unit(pid: u32) -> Option<String> {
+    let cgroup = fs::read_to_string(format!("/proc/{pid}/cgroup")).ok()?;
+    cgroup_unit(cgroup.lines().find_map(|line| line.strip_prefix("0::"))?)
+}
+
+// Scan /proc; returns affected services and processes outside any service.
+pub(crate) fn scan_stale_processes() -> (Vec<StaleService>, Vec<String>) {
+    let mut services: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    let mut others = Vec::new();
+    let pids = fs::read_dir("/proc")
+        .map(|entries| {
+            entries
+                .filter_map(|entry| entry.ok()?.file_name().to_str()?.parse::<u32>().ok())
+                .collect::<Vec<_>>()
+        })
+        .unwrap_or_default();
+    for pid in pids {
+        if pid == std::process::id() || !uses_replaced_files(pid) {
+            continue;
+        }
+        let label = format!("{} ({pid})", process_name(pid));
+        match systemd_unit(pid) {
+            // PID 1 is re-executed rather than restarted.
+            _ if pid == 1 => services.entry("systemd".into()).or_default().push(label),
+            Some(unit) => services.entry(unit).or_default().push(label),
+            None => others.push(label),
+        }
+    }
+    let services = services
+        .into_iter()
+        .map(|(unit, processes)| StaleService {
+            unit,
+            processes,
+            status: "pending",
+        })
+        .collect();
+    (services, others)
+}
+
+pub(crate) fn restart_safe(unit: &str) -> bool {
+    !UNSAFE_UNITS
+        .iter()
+        .any(|u| unit == *u || (u.ends_with('@') && unit.starts_with(u)))
+}
+
+// Report stale services and, with --restart-services, restart the safe ones.
+pub(crate) fn handle_stale_services(report: &mut RunReport, opts: &Options) {
+    let (mut services, others) = scan_stale_processes();
+    if services.is_empty() && others.is_empty() {
+        color_print!(GREEN, "✅ No running services use replaced files\n");
+        return;
+    }
+    color_print!(YELLOW, "\n🔄 Still using replaced files:\n");
+    for service in &mut services {
+        color_print!(
+            "",
+            "  {:<32} {}\n",
+            service.unit,
+            service.processes.join(", ")
+        );
+        if !opts.restart_services {
+            continue;
+        }
+        let cmd = match service.unit.as_str() {
+            "systemd" => argv(&["systemctl", "daemon-reexec"]),
+            unit if restart_safe(unit) => argv(&["systemctl", "try-restart", unit]),
+            _ => {
+                service.status = "skipped";
+                continue;
+            }
+        };
+        let step = run(&cmd, "", None);
+        service.status = if step.succeeded() {
+            "restarted"
+        } else {
+            "failed"
+        };
+        report.steps.push(step);
+    }
+    if !others.is_empty() {
+        color_print!(
+            YELLOW,
+            "  Other processes (restart manually or log out): {}\n",
+            others.join(", ")
+        );
+    }
+    let skipped: Vec<&str> = services
+        .iter()
+        .filter(|s| s.status == "skipped")
+        .map(|s| s.unit.as_str())
+        .collect();
+    if !skipped.is_empty() {
+        report.warn(format!(
+            "Not restarted automatically (would end sessions): {}",
+            skipped.join(", ")
+        ));
+    } else if !opts.restart_services && !services.is_empty() {
+        color_print!(CYAN, "ℹ️  Re-run with --restart-services to restart them\n");
+    }
+    report.stale_services = services;
+    report.stale_processes = others;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_deleted_system_files_count_as_replaced() {
+        assert!(is_replaced(
+            "/usr/lib/x86_64-linux-gnu/libssl.so.3 (deleted)"
+        ));
+        assert!(is_replaced("/lib/systemd/systemd-journald (deleted)"));
+        assert!(!is_replaced("/usr/lib/x86_64-linux-gnu/libssl.so.3"));
+        assert!(!is_replaced("/dev/shm/pulse-shm-1234 (deleted)"));
+        assert!(!is_replaced("/memfd:wayland-cursor (deleted)"));
+    }
+
+    #[test]
+    fn user_services_belong_to_their_session() {
+        assert_eq!(
+            cgroup_unit("/system.slice/nginx.service").as_deref(),
+            Some("nginx.service")
+        );
+        assert_eq!(
+            cgroup_unit("/user.slice/user-1000.slice/user@1000.service/app.slice/foo.service")
+                .as_deref(),
+            Some("user@1000.service")
+        );
+        assert_eq!(
+            cgroup_unit("/user.slice/user-1000.slice/session-2.scope"),
+            None
+        );
+    }
+
+    #[test]
+    fn session_units_are_not_restarted() {
+        assert!(restart_safe("nginx.service"));
+        assert!(restart_safe("dbus-org.freedesktop.resolve1.service"));
+        assert!(!restart_safe("dbus.service"));
+        assert!(!restart_safe("gdm3.service"));
+        assert!(!restart_safe("user@1000.service"));
+    }
+}
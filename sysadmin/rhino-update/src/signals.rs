@@ -0,0 +1,167 @@
+use std::fs;
+use std::sync::atomic::{AtomicBool, AtomicI32, AtomicU32, Ordering};
+
+use crate::backends::argv;
+use crate::cli::Options;
+use crate::exec::{command_exists, run, runner};
+use crate::report::{emit_report, RunReport};
+use crate::ui::{RED, YELLOW};
+
+// Signals ---------------------------------------------------------------------
+// Steps run in their own process group, so Ctrl-C reaches only rhino-update.
+// SIGINT, SIGTERM and SIGHUP are passed on to the running step, except while
+// dpkg is working in it: then the step is allowed to finish unless the signal
+// is repeated. No further steps start; an interrupted dpkg is finished with
+// `dpkg --configure -a`, and the run exits 128+signal as "interrupted".
+pub(crate) const SIGHUP: i32 = 1;
+pub(crate) const SIGINT: i32 = 2;
+pub(crate) const SIGKILL: i32 = 9;
+pub(crate) const SIGTERM: i32 = 15;
+pub(crate) const DPKG_UPDATES: &str = "/var/lib/dpkg/updates";
+
+pub(crate) static PENDING_SIGNAL: AtomicI32 = AtomicI32::new(0);
+pub(crate) static SIGNAL_COUNT: AtomicU32 = AtomicU32::new(0);
+// Set while `dpkg --configure -a` repairs an interrupted run.
+pub(crate) static RECOVERING: AtomicBool = AtomicBool::new(false);
+
+extern "C" {
+    fn kill(pid: i32, sig: i32) -> i32;
+    fn signal(signum: i32, handler: extern "C" fn(i32)) -> usize;
+}
+
+// Only touches atomics, which is async-signal-safe.
+extern "C" fn on_signal(signum: i32) {
+    PENDING_SIGNAL.store(signum, Ordering::SeqCst);
+    SIGNAL_COUNT.fetch_add(1, Ordering::SeqCst);
+}
+
+pub fn install_signal_handlers() {
+    for signum in [SIGHUP, SIGINT, SIGTERM] {
+        // SAFETY: on_signal is async-signal-safe.
+        unsafe {
+            signal(signum, on_signal);
+        }
+    }
+}
+
+pub fn interrupted() -> Option<i32> {
+    Some(PENDING_SIGNAL.load(Ordering::SeqCst)).filter(|&signum| signum != 0)
+}
+
+pub(crate) fn signal_name(signum: i32) -> &'static str {
+    match signum {
+        SIGHUP => "SIGHUP",
+        SIGINT => "SIGINT",
+        SIGTERM => "SIGTERM",
+        _ => "a signal",
+    }
+}
+
+pub(crate) fn signal_group(pgid: u32, signal: i32) {
+    // SAFETY: kill(2) has no memory-safety preconditions.
+    unsafe {
+        kill(-(pgid as i32), signal);
+    }
+}
+
+// Whether a dpkg process belongs to the step's process group.
+pub(crate) fn dpkg_running_in(pgid: u32) -> bool {
+    let Ok(entries) = fs::read_dir("/proc") else {
+        return false;
+    };
+    entries.flatten().any(|entry| {
+        let stat = fs::read_to_string(entry.path().join("stat")).unwrap_or_default();
+        // "pid (comm) state ppid pgrp …"; comm may itself contain spaces.
+        let (Some(open), Some(close)) = (stat.find('('), stat.rfind(')')) else {
+            return false;
+        };
+        let pgrp = stat[close + 1..].split_whitespace().nth(2);
+        &stat[open + 1..close] == "dpkg" && pgrp == Some(pgid.to_string().as_str())
+    })
+}
+
+// Called from wait_child's poll loop with the signal count seen at spawn.
+pub(crate) fn forward_signal(pgid: u32, seen_at_spawn: u32, warned: &mut bool) {
+    let Some(signum) = interrupted() else {
+        return;
+    };
+    let repeated = SIGNAL_COUNT
+        .load(Ordering::SeqCst)
+        .saturating_sub(seen_at_spawn)
+        >= 2;
+    if repeated || !dpkg_running_in(pgid) {
+        signal_group(pgid, signum);
+    } else if !*warned {
+        *warned = true;
+        color_print!(
+            YELLOW,
+            "\n⚠️  {} received while dpkg is working; letting it finish (repeat to force)\n",
+            signal_name(signum)
+        );
+    }
+}
+
+// dpkg journals unfinished work in DPKG_UPDATES; `dpkg --audit` lists
+// packages left unpacked or half-configured.
+pub(crate) fn dpkg_needs_recovery() -> bool {
+    if !command_exists("dpkg") {
+        return false;
+    }
+    let journal = fs::read_dir(DPKG_UPDATES).is_ok_and(|mut dir| dir.next().is_some());
+    journal
+        || runner()
+            .output(&argv(&["dpkg", "--audit"]))
+            .is_ok_and(|out| !out.stdout.trim_ascii().is_empty())
+}
+
+pub(crate) fn recover_dpkg(report: &mut RunReport) -> Result<(), i32> {
+    if !dpkg_needs_recovery() {
+        return Ok(());
+    }
+    let mut step = run(
+        &argv(&["dpkg", "--configure", "-a"]),
+        "Finishing interrupted dpkg configuration …",
+        None,
+    );
+    step.backend = Some("apt");
+    let code = step.failure_code();
+    let ok = step.succeeded();
+    report.steps.push(step);
+    if ok {
+        Ok(())
+    } else {
+        Err(code)
+    }
+}
+
+// A timed-out step is never stopped while dpkg works, but apt runs dpkg more
+// than once and the timeout may have landed in between.
+pub(crate) fn recover_after_timeout(report: &mut RunReport) {
+    if recover_dpkg(report).is_err() {
+        color_print!(
+            RED,
+            "❌ dpkg is still half-configured; run `dpkg --configure -a`.\n"
+        );
+    }
+}
+
+// Leave the package database consistent, then record the run as interrupted.
+// Returns the exit code, 128+signum.
+pub(crate) fn interrupt(report: &mut RunReport, opts: &Options, signum: i32) -> i32 {
+    color_print!(
+        YELLOW,
+        "\n⏹  Interrupted by {}; no further steps were started.\n",
+        signal_name(signum)
+    );
+    RECOVERING.store(true, Ordering::SeqCst);
+    if recover_dpkg(report).is_err() {
+        color_print!(
+            RED,
+            "❌ dpkg is still half-configured; run `dpkg --configure -a`.\n"
+        );
+    }
+    let code = 128 + signum;
+    report.finish("interrupted", code);
+    emit_report(report, opts);
+    code
+}
@@ -0,0 +1,108 @@
+use std::fs;
+
+use crate::backends::argv;
+use crate::exec::{capture, command_exists, run, Step};
+
+// Pre-update snapshots --------------------------------------------------------
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SnapshotMode {
+    #[default]
+    Off,
+    Auto,
+    Timeshift,
+    Btrfs,
+}
+
+#[derive(Clone, Debug)]
+pub struct Snapshot {
+    pub kind: &'static str,
+    // Timeshift snapshot name, or the path of the Btrfs snapshot subvolume.
+    pub name: String,
+}
+
+pub(crate) const BTRFS_SNAPSHOT_DIR: &str = "/.snapshots";
+
+pub(crate) fn root_fstype() -> Option<String> {
+    let mounts = fs::read_to_string("/proc/self/mounts").ok()?;
+    // The last matching entry is the one currently mounted over /.
+    mounts.lines().rev().find_map(|line| {
+        let mut cols = line.split_whitespace();
+        let (_, mountpoint, fstype) = (cols.next()?, cols.next()?, cols.next()?);
+        (mountpoint == "/").then(|| fstype.to_string())
+    })
+}
+
+// Auto prefers a native Btrfs snapshot and falls back to Timeshift.
+pub(crate) fn resolve_snapshot_mode(mode: SnapshotMode) -> Result<SnapshotMode, String> {
+    let btrfs = root_fstype().as_deref() == Some("btrfs") && command_exists("btrfs");
+    let timeshift = command_exists("timeshift");
+    match mode {
+        SnapshotMode::Auto if btrfs => Ok(SnapshotMode::Btrfs),
+        SnapshotMode::Auto if timeshift => Ok(SnapshotMode::Timeshift),
+        SnapshotMode::Auto => {
+            Err("no snapshot tool: / is not Btrfs and timeshift is not installed".into())
+        }
+        SnapshotMode::Btrfs if !btrfs => {
+            Err("/ is not a Btrfs filesystem (or btrfs-progs is missing)".into())
+        }
+        SnapshotMode::Timeshift if !timeshift => Err("timeshift is not installed".into()),
+        other => Ok(other),
+    }
+}
+
+pub(crate) fn take_snapshot(mode: SnapshotMode, run_id: &str) -> (Step, Option<Snapshot>) {
+    let tag = format!("rhino-update {run_id}");
+    match mode {
+        SnapshotMode::Btrfs => {
+            let path = format!("{BTRFS_SNAPSHOT_DIR}/rhino-update-{run_id}");
+            let _ = fs::create_dir_all(BTRFS_SNAPSHOT_DIR);
+            let cmd = argv(&["btrfs", "subvolume", "snapshot", "-r", "/", &path]);
+            let step = run(&cmd, "Taking Btrfs snapshot of / …", None);
+            let snapshot = step.succeeded().then_some(Snapshot {
+                kind: "btrfs",
+                name: path,
+            });
+            (step, snapshot)
+        }
+        _ => {
+            let cmd = argv(&[
+                "timeshift",
+                "--create",
+                "--rsync",
+                "--scripted",
+                "--tags",
+                "O",
+                "--comments",
+                &tag,
+            ]);
+            let step = run(&cmd, "Taking Timeshift snapshot …", None);
+            let snapshot = step
+                .succeeded()
+                .then(|| find_timeshift_snapshot(&tag))
+                .flatten()
+                .map(|name| Snapshot {
+                    kind: "timeshift",
+                    name,
+                });
+            (step, snapshot)
+        }
+    }
+}
+
+// `timeshift --list` rows look like "0    >  2026-10-16_03-00-01  O  <comment>".
+pub(crate) fn find_timeshift_snapshot(comment: &str) -> Option<String> {
+    let list = capture(&["timeshift", "--list", "--scripted"])?;
+    list.lines()
+        .filter(|line| line.contains(comment))
+        .find_map(|line| line.split_whitespace().find(|col| is_timeshift_name(col)))
+        .map(str::to_string)
+}
+
+pub(crate) fn is_timeshift_name(s: &str) -> bool {
+    s.len() == 19
+        && s.chars().enumerate().all(|(i, c)| match i {
+            4 | 7 | 13 | 16 => c == '-',
+            10 => c == '_',
+            _ => c.is_ascii_digit(),
+        })
+}
@@ -0,0 +1,84 @@
+use std::env;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::json::{parse_json, Json};
+use crate::report::RunReport;
+
+// Run store -------------------------------------------------------------------
+pub const STATE_DIR: &str = "/var/lib/rhino-update";
+
+// RHINO_UPDATE_STATE_DIR relocates the store, e.g. for tests.
+pub fn runs_dir() -> PathBuf {
+    env::var_os("RHINO_UPDATE_STATE_DIR")
+        .map_or_else(|| PathBuf::from(STATE_DIR), PathBuf::from)
+        .join("runs")
+}
+
+pub(crate) fn save_run(report: &RunReport) -> io::Result<PathBuf> {
+    let dir = runs_dir();
+    fs::create_dir_all(&dir)?;
+    let path = dir.join(format!("{}.json", report.run_id));
+    fs::write(&path, format!("{}\n", report.to_json()))?;
+    Ok(path)
+}
+
+pub fn read_run(path: &Path) -> Result<Json, String> {
+    fs::read_to_string(path)
+        .map_err(|e| e.to_string())
+        .and_then(|text| parse_json(&text))
+        .map_err(|e| format!("{}: {e}", path.display()))
+}
+
+// Every stored run, newest first; unreadable records are skipped.
+pub fn list_runs() -> Result<Vec<Json>, String> {
+    let mut paths: Vec<PathBuf> = fs::read_dir(runs_dir())
+        .map_err(|e| format!("{}: {e}", runs_dir().display()))?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+        .collect();
+    // Run IDs start with a UTC timestamp, so names sort chronologically.
+    paths.sort();
+    Ok(paths
+        .iter()
+        .rev()
+        .filter_map(|p| read_run(p).ok())
+        .collect())
+}
+
+// Load RUN_ID, or the newest stored run (of `mode`, if given).
+pub fn load_run(run_id: Option<&str>, mode: Option<&str>) -> Result<Json, String> {
+    if let Some(id) = run_id {
+        // A run ID names a file in the store and nothing outside it.
+        if id.is_empty() || id.contains('/') || id.contains("..") {
+            return Err(format!("invalid run ID: {id}"));
+        }
+        return read_run(&runs_dir().join(format!("{id}.json")));
+    }
+    list_runs()?
+        .into_iter()
+        .find(|run| mode.is_none() || run.get("mode").as_str() == mode)
+        .ok_or_else(|| {
+            format!(
+                "no recorded {}runs in {}",
+                mode.map(|m| format!("{m} ")).unwrap_or_default(),
+                runs_dir().display()
+            )
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn run_ids_stay_inside_the_store() {
+        for id in ["../../etc/shadow", "/etc/passwd", "..", ""] {
+            assert_eq!(
+                load_run(Some(id), None).err(),
+                Some(format!("invalid run ID: {id}"))
+            );
+        }
+    }
+}
@@ -0,0 +1,283 @@
+use std::collections::BTreeMap;
+
+use crate::backends::{all_backends, Action, PackageChange};
+use crate::exec::Step;
+use crate::history::format_secs;
+use crate::holds::held_back_status;
+use crate::report::RunReport;
+use crate::ui::{BOLD, CYAN, GREEN, MAGENTA, RED, YELLOW};
+
+// Run summary -----------------------------------------------------------------
+// Printed at the end of an update: what changed per backend, how much was
+// downloaded and freed, how long each step took, what warned, and whether a
+// reboot or service restart is still needed.
+
+// "45.2 MB", "950 kB", "0 B"; apt and flatpak both count in powers of 1000.
+pub(crate) fn parse_size(text: &str) -> Option<u64> {
+    let mut parts = text.split_whitespace();
+    let number: f64 = parts.next()?.replace(',', "").parse().ok()?;
+    let scale = match parts.next()? {
+        "B" | "bytes" => 1e0,
+        "kB" => 1e3,
+        "MB" => 1e6,
+        "GB" => 1e9,
+        "TB" => 1e12,
+        _ => return None,
+    };
+    Some((number * scale) as u64)
+}
+
+pub(crate) fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "kB", "MB", "GB", "TB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1000.0 && unit < UNITS.len() - 1 {
+        size /= 1000.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{bytes} B")
+    } else {
+        format!("{size:.1} {}", UNITS[unit])
+    }
+}
+
+// Bytes downloaded and disk space freed according to apt's output: "Fetched
+// 45.2 MB in 3s (15.0 MB/s)" and "After this operation, 12.0 MB disk space
+// will be freed.". Non-interactive flatpak prints no sizes (see
+// RunReport::downloaded).
+pub(crate) fn transfer_sizes(output: &str) -> (u64, u64) {
+    let (mut fetched, mut freed) = (0, 0);
+    for line in output.lines().map(str::trim) {
+        if let Some(rest) = line.strip_prefix("Fetched ") {
+            fetched += rest.split(" in ").next().and_then(parse_size).unwrap_or(0);
+        } else if let Some(rest) = line.strip_prefix("After this operation, ") {
+            let size = rest.strip_suffix(" disk space will be freed.");
+            freed += size.and_then(parse_size).unwrap_or(0);
+        }
+    }
+    (fetched, freed)
+}
+
+// Whether the step ran its backend's cleanup command. Upgrades that replace
+// packages can free space too, but that is not what cleanup freed.
+pub(crate) fn is_cleanup(step: &Step) -> bool {
+    all_backends().iter().any(|backend| {
+        step.backend == Some(backend.name()) && backend.cleanup().as_ref() == Some(&step.argv)
+    })
+}
+
+// apt's "W: …" and flatpak's "Warning: …" lines from a step's stderr.
+pub(crate) fn step_warnings(step: &Step) -> Vec<String> {
+    let mut warnings: Vec<String> = Vec::new();
+    for line in step.stderr_tail.lines().map(str::trim) {
+        if (line.starts_with("W: ") || line.starts_with("Warning: "))
+            && !warnings.iter().any(|w| w == line)
+        {
+            warnings.push(line.to_string());
+        }
+    }
+    warnings
+}
+
+// What a step changed: its parsed transactions, or the inventory diff when
+// the backend's output said nothing.
+pub(crate) fn step_changes(step: &Step) -> &[PackageChange] {
+    if step.transactions.is_empty() {
+        &step.packages
+    } else {
+        &step.transactions
+    }
+}
+
+// Upgraded, installed and removed packages per backend.
+pub(crate) fn package_counts(steps: &[Step]) -> BTreeMap<&'static str, [usize; 3]> {
+    let mut counts: BTreeMap<&'static str, [usize; 3]> = BTreeMap::new();
+    for change in steps.iter().flat_map(step_changes) {
+        let column = match change.action {
+            Action::Upgrade => 0,
+            Action::Install => 1,
+            Action::Remove => 2,
+        };
+        counts.entry(change.backend).or_default()[column] += 1;
+    }
+    counts
+}
+
+pub(crate) fn print_summary(report: &RunReport) {
+    color_print!(format!("{MAGENTA}{BOLD}"), "\n📊 Summary\n");
+
+    let counts = package_counts(&report.steps);
+    color_print!(
+        BOLD,
+        "  {:<10} {:<12} {:>8} {:>9} {:>7}\n",
+        "BACKEND",
+        "STATUS",
+        "UPGRADED",
+        "INSTALLED",
+        "REMOVED"
+    );
+    for &(name, available) in &report.backends {
+        let status = report.backend_status(name, available);
+        let color = match status {
+            "success" => GREEN,
+            "failed" => RED,
+            _ => YELLOW,
+        };
+        let [upgraded, installed, removed] = counts.get(name).copied().unwrap_or_default();
+        color_print!("", "  {:<10} ", name);
+        color_print!(color, "{:<12}", status);
+        color_print!("", " {:>8} {:>9} {:>7}\n", upgraded, installed, removed);
+    }
+
+    let (mut fetched, mut freed) = (report.downloaded, 0);
+    for step in &report.steps {
+        let (step_fetched, step_freed) = transfer_sizes(&step.output);
+        fetched += step_fetched;
+        if is_cleanup(step) {
+            freed += step_freed;
+        }
+    }
+    color_print!(CYAN, "\n  {:<18}", "Downloaded");
+    color_print!("", " {}\n", format_size(fetched));
+    color_print!(CYAN, "  {:<18}", "Disk space freed");
+    color_print!("", " {}\n", format_size(freed));
+
+    if !report.steps.is_empty() {
+        color_print!(BOLD, "\n  {:<52} {:>8}\n", "STEP", "TIME");
+    }
+    for step in &report.steps {
+        let (color, mark) = if step.succeeded() {
+            (GREEN, "✅")
+        } else {
+            (RED, "❌")
+        };
+        let mut label = if step.description.is_empty() {
+            step.argv.join(" ")
+        } else {
+            step.description.clone()
+        };
+        if step.attempts > 1 {
+            label = format!("{label} ({} attempts)", step.attempts);
+        }
+        if label.chars().count() > 49 {
+            label = format!("{}…", label.chars().take(48).collect::<String>());
+        }
+        color_print!(color, "  {} ", mark);
+        color_print!(
+            "",
+            "{:<49} {:>8}\n",
+            label,
+            format_secs(step.duration.as_secs_f64())
+        );
+    }
+
+    let cves: usize = report.cves.iter().map(|f| f.cves.len()).sum();
+    color_print!(CYAN, "\n  {:<18}", "CVEs fixed");
+    color_print!("", " {}\n", cves);
+    color_print!(CYAN, "  {:<18}", "Held back");
+    color_print!("", " {}\n", report.held_back.len());
+    for change in &report.held_back {
+        color_print!(
+            "",
+            "    • {} {} ({})\n",
+            change.backend,
+            change.name,
+            held_back_status(change)
+        );
+    }
+    color_print!(CYAN, "  {:<18}", "Warnings");
+    color_print!("", " {}\n", report.warnings.len());
+    for warning in &report.warnings {
+        color_print!(YELLOW, "    • {}\n", warning);
+    }
+
+    color_print!(CYAN, "  {:<18}", "Reboot");
+    match &report.reboot {
+        Some(reboot) if !reboot.reasons.is_empty() => {
+            color_print!(YELLOW, " required ({})\n", reboot.decision)
+        }
+        Some(_) => color_print!(GREEN, " not required\n"),
+        None => color_print!("", " not checked\n"),
+    }
+    let mut pending: Vec<&str> = report
+        .stale_services
+        .iter()
+        .filter(|s| s.status != "restarted")
+        .map(|s| s.unit.as_str())
+        .collect();
+    pending.extend(report.stale_processes.iter().map(String::as_str));
+    color_print!(CYAN, "  {:<18}", "Service restarts");
+    if pending.is_empty() {
+        color_print!(GREEN, " none needed\n\n");
+    } else {
+        color_print!(YELLOW, " needed: {}\n\n", pending.join(", "));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, SystemTime};
+
+    use super::*;
+    use crate::backends::argv;
+
+    #[test]
+    fn sizes_come_from_apt_output() {
+        let output = "\
+Fetched 45.2 MB in 3s (15.0 MB/s)
+After this operation, 1,200 kB disk space will be freed.
+0 upgraded, 0 newly installed, 2 to remove and 0 not upgraded.
+";
+        assert_eq!(transfer_sizes(output), (45_200_000, 1_200_000));
+        assert_eq!(parse_size("98.4\u{a0}MB"), Some(98_400_000));
+        assert_eq!(format_size(143_600_000), "143.6 MB");
+        assert_eq!(format_size(512), "512 B");
+    }
+
+    #[test]
+    fn only_cleanup_steps_free_space() {
+        let step = |cmd: &[&str]| {
+            let mut step = Step::new(&argv(cmd), "", SystemTime::now(), Duration::ZERO);
+            step.backend = Some("apt");
+            step
+        };
+        assert!(is_cleanup(&step(&[
+            "apt-get",
+            "-y",
+            "autoremove",
+            "--purge"
+        ])));
+        assert!(!is_cleanup(&step(&["apt-get", "-y", "dist-upgrade"])));
+    }
+
+    #[test]
+    fn counts_prefer_parsed_transactions() {
+        let change = |backend, name: &str, action| PackageChange {
+            backend,
+            name: name.to_string(),
+            action,
+            old_version: None,
+            new_version: None,
+        };
+        let step = |packages, transactions| {
+            let mut step = Step::new(&argv(&["true"]), "", SystemTime::now(), Duration::ZERO);
+            step.packages = packages;
+            step.transactions = transactions;
+            step
+        };
+        let steps = [
+            step(
+                vec![change("apt", "hello", Action::Upgrade)],
+                vec![
+                    change("apt", "hello", Action::Upgrade),
+                    change("pacstall", "neofetch", Action::Install),
+                ],
+            ),
+            step(vec![change("apt", "oldlib1", Action::Remove)], Vec::new()),
+        ];
+        let counts = package_counts(&steps);
+        assert_eq!(counts.get("apt"), Some(&[1, 0, 1]));
+        assert_eq!(counts.get("pacstall"), Some(&[0, 1, 0]));
+    }
+}
@@ -0,0 +1,210 @@
+use std::env;
+use std::fmt;
+use std::io::{self, IsTerminal, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use crate::log::log_printed;
+
+// ANSI helpers ----------------------------------------------------------------
+pub const RESET: &str = "\x1b[0m";
+pub const BOLD: &str = "\x1b[1m";
+pub const RED: &str = "\x1b[31m";
+pub const GREEN: &str = "\x1b[32m";
+pub const YELLOW: &str = "\x1b[33m";
+pub const BLUE: &str = "\x1b[34m";
+pub const MAGENTA: &str = "\x1b[35m";
+pub const CYAN: &str = "\x1b[36m";
+
+// Human-readable output moves to stderr when stdout carries the JSON report.
+pub(crate) static UI_TO_STDERR: AtomicBool = AtomicBool::new(false);
+// Decided once by init_styling: escape codes, and emoji or ASCII symbols.
+pub(crate) static UI_COLOR: AtomicBool = AtomicBool::new(false);
+pub(crate) static UI_ASCII: AtomicBool = AtomicBool::new(false);
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ColorMode {
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
+// Auto colours a terminal unless NO_COLOR is set or TERM is dumb;
+// CLICOLOR_FORCE colours even a pipe (https://no-color.org, bixense.com/clicolors).
+// init_styling passes in the variables from the environment.
+pub(crate) fn use_color(
+    mode: ColorMode,
+    is_tty: bool,
+    no_color: Option<&str>,
+    clicolor_force: Option<&str>,
+    term: Option<&str>,
+) -> bool {
+    let set = |value: Option<&str>| value.is_some_and(|v| !v.is_empty());
+    match mode {
+        ColorMode::Always => true,
+        ColorMode::Never => false,
+        ColorMode::Auto if set(no_color) => false,
+        ColorMode::Auto if set(clicolor_force) => clicolor_force != Some("0"),
+        ColorMode::Auto => is_tty && term != Some("dumb"),
+    }
+}
+
+// The first of LC_ALL, LC_CTYPE and LANG that is set decides the charset.
+pub(crate) fn utf8_locale() -> bool {
+    ["LC_ALL", "LC_CTYPE", "LANG"]
+        .iter()
+        .filter_map(|name| env::var(name).ok())
+        .find(|value| !value.is_empty())
+        .is_some_and(|locale| {
+            let locale = locale.to_ascii_lowercase();
+            locale.contains("utf-8") || locale.contains("utf8")
+        })
+}
+
+pub fn init_styling(mode: ColorMode) {
+    let is_tty = if UI_TO_STDERR.load(Ordering::Relaxed) {
+        io::stderr().is_terminal()
+    } else {
+        io::stdout().is_terminal()
+    };
+    let var = |name: &str| env::var(name).ok();
+    let color = use_color(
+        mode,
+        is_tty,
+        var("NO_COLOR").as_deref(),
+        var("CLICOLOR_FORCE").as_deref(),
+        var("TERM").as_deref(),
+    );
+    UI_COLOR.store(color, Ordering::Relaxed);
+    UI_ASCII.store(!utf8_locale(), Ordering::Relaxed);
+}
+
+pub(crate) const ASCII_SYMBOLS: [(&str, &str); 26] = [
+    ("✅", "[ok]"),
+    ("❌", "[x]"),
+    ("⚠️", "[!]"),
+    ("ℹ️", "[i]"),
+    ("🦏", "*"),
+    ("➜", "=>"),
+    ("▶", ">"),
+    ("⏭", ">>"),
+    ("⏸", "||"),
+    ("⏹", "[stop]"),
+    ("↻", "[retry]"),
+    ("→", "->"),
+    ("…", "..."),
+    ("–", "-"),
+    ("—", "-"),
+    ("•", "*"),
+    ("📸", "[snapshot]"),
+    ("📋", "[plan]"),
+    ("📊", "[summary]"),
+    ("📜", "[changelog]"),
+    ("🔄", "[restart]"),
+    ("🔁", "[reboot]"),
+    ("🔒", "[held]"),
+    ("🔓", "[released]"),
+    ("🛡", "[security]"),
+    ("🔐", "[root]"),
+];
+
+pub(crate) fn ascii_fallback(text: &str) -> String {
+    let mut out = text.to_string();
+    for (symbol, ascii) in ASCII_SYMBOLS {
+        out = out.replace(symbol, ascii);
+    }
+    out.chars()
+        .filter(|c| *c != '\u{fe0f}')
+        .map(|c| if c.is_ascii() { c } else { '?' })
+        .collect()
+}
+
+pub fn ui_print(args: fmt::Arguments) {
+    let raw = args.to_string();
+    log_printed(&raw);
+    let mut text = if UI_COLOR.load(Ordering::Relaxed) {
+        raw
+    } else {
+        strip_ansi(&raw)
+    };
+    if UI_ASCII.load(Ordering::Relaxed) {
+        text = ascii_fallback(&text);
+    }
+    if UI_TO_STDERR.load(Ordering::Relaxed) {
+        let _ = io::stderr().write_all(text.as_bytes());
+    } else {
+        let _ = io::stdout().write_all(text.as_bytes());
+    }
+}
+
+pub fn strip_ansi(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c == '\x1b' {
+            // Skip "ESC [ params letter".
+            for c in chars.by_ref() {
+                if c.is_ascii_alphabetic() {
+                    break;
+                }
+            }
+        } else {
+            out.push(c);
+        }
+    }
+    out
+}
+
+// Print in `color` (any Display, usually one of the constants above).
+#[macro_export]
+macro_rules! color_print {
+    ($color:expr, $($arg:tt)*) => {{
+        $crate::ui::ui_print(format_args!(
+            "{}{}{}",
+            $color,
+            format_args!($($arg)*),
+            $crate::ui::RESET
+        ));
+    }};
+}
+
+// Ask a yes/no question on the terminal; an empty answer takes `default`.
+pub(crate) fn confirm(question: &str, default: bool) -> bool {
+    color_print!(
+        YELLOW,
+        "{} {} ",
+        question,
+        if default { "[Y/n]" } else { "[y/N]" }
+    );
+    let mut answer = String::new();
+    if io::stdin().read_line(&mut answer).is_err() {
+        return false;
+    }
+    match answer.trim().to_ascii_lowercase().as_str() {
+        "" => default,
+        answer => matches!(answer, "y" | "yes"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn color_follows_mode_then_environment_then_terminal() {
+        let auto = |is_tty, no_color, force, term| {
+            use_color(ColorMode::Auto, is_tty, no_color, force, term)
+        };
+        assert!(use_color(ColorMode::Always, false, Some("1"), None, None));
+        assert!(!use_color(ColorMode::Never, true, None, Some("1"), None));
+        assert!(auto(true, None, None, None));
+        assert!(auto(true, None, None, Some("xterm-256color")));
+        assert!(!auto(false, None, None, None));
+        assert!(!auto(true, None, None, Some("dumb")));
+        assert!(auto(false, None, Some("1"), Some("dumb")));
+        assert!(!auto(false, None, Some("0"), None));
+        // An empty NO_COLOR does not count; any other value beats CLICOLOR_FORCE.
+        assert!(auto(false, Some(""), Some("1"), None));
+        assert!(!auto(true, Some("1"), Some("1"), None));
+    }
+}
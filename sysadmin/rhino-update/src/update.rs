@@ -0,0 +1,258 @@
+use crate::backends::{step_transactions, PackageBackend};
+use crate::changelog::{
+    collect_changelogs, find_cves, load_cve_feed, print_changelogs, print_cves,
+};
+use crate::cli::Options;
+use crate::exec::run_retrying;
+use crate::holds::{apply_holds, record_held_back};
+use crate::hooks::run_hooks;
+use crate::log::{init_logging, LOG_FILE};
+use crate::preflight::run_preflight;
+use crate::privilege::require_root;
+use crate::reboot::handle_reboot;
+use crate::report::{abort, defer, emit_report, RunReport};
+use crate::rollback::net_changes;
+use crate::services::handle_stale_services;
+use crate::signals::{
+    install_signal_handlers, interrupt, interrupted, recover_after_timeout, recover_dpkg,
+};
+use crate::snapshot::{resolve_snapshot_mode, take_snapshot, SnapshotMode};
+use crate::summary::{print_summary, step_warnings};
+use crate::ui::{BOLD, GREEN, MAGENTA, RED, YELLOW};
+use crate::window::WindowCheck;
+
+// Update ----------------------------------------------------------------------
+// Pre-flight, snapshot and holds, then each backend's refresh, upgrade and
+// cleanup steps, then service restarts, reboot and hooks. Every step is
+// recorded in the returned run report, whose exit code is the command's.
+pub fn cmd_update(opts: &Options, backends: &[Box<dyn PackageBackend>]) -> RunReport {
+    let mut report = RunReport::new("update");
+    update(&mut report, opts, backends);
+    report
+}
+
+fn update(report: &mut RunReport, opts: &Options, backends: &[Box<dyn PackageBackend>]) -> i32 {
+    if let Err(code) = require_root(opts) {
+        report.finish("failed", code);
+        return code;
+    }
+
+    color_print!(
+        format!("{MAGENTA}{BOLD}"),
+        "🦏 Rhino Linux Update & Cleanup\n\n"
+    );
+
+    init_logging(opts.log_file.as_deref().unwrap_or(LOG_FILE), report);
+    install_signal_handlers();
+    report.backends = backends
+        .iter()
+        .map(|b| (b.name(), b.is_available()))
+        .collect();
+    let available: Vec<&dyn PackageBackend> = backends
+        .iter()
+        .filter(|b| b.is_available())
+        .map(|b| b.as_ref())
+        .collect();
+    // Always track packages: the recorded transaction is what rollback undoes.
+    let tracked = Some(backends);
+
+    let mut windows = WindowCheck::new(opts);
+    if let Err(reason) = windows.check() {
+        return defer(report, opts, &reason);
+    }
+    if let Err(code) = run_preflight(report, opts) {
+        return abort(report, opts, code);
+    }
+    if let Err(code) = run_hooks(report, &opts.pre_hooks, "pre-update", None) {
+        color_print!(RED, "❌ Pre-update hook failed; nothing was changed.\n");
+        return abort(report, opts, code);
+    }
+
+    if opts.snapshot != SnapshotMode::Off {
+        let mode = match resolve_snapshot_mode(opts.snapshot) {
+            Ok(mode) => mode,
+            Err(msg) => {
+                color_print!(RED, "❌ Cannot take a snapshot: {}\n", msg);
+                return abort(report, opts, 1);
+            }
+        };
+        let (step, snapshot) = take_snapshot(mode, &report.run_id);
+        let code = step.failure_code();
+        report.steps.push(step);
+        match snapshot {
+            Some(snap) => {
+                color_print!(GREEN, "📸 Snapshot {} ({})\n\n", snap.name, snap.kind);
+                report.snapshot = Some(snap);
+            }
+            None => {
+                color_print!(
+                    RED,
+                    "❌ Snapshot failed; refusing to update without a rollback point.\n"
+                );
+                return abort(report, opts, code);
+            }
+        }
+    }
+
+    // A previous run may have been killed mid-dpkg; apt refuses to work
+    // until that is finished.
+    if let Err(code) = recover_dpkg(report) {
+        color_print!(
+            RED,
+            "❌ Could not finish an earlier interrupted dpkg run.\n"
+        );
+        return abort(report, opts, code);
+    }
+
+    if let Err(code) = apply_holds(report, backends, &opts.holds) {
+        color_print!(
+            RED,
+            "❌ Could not hold packages; refusing to upgrade them.\n"
+        );
+        return abort(report, opts, code);
+    }
+
+    // A failing backend skips its remaining steps; the others carry on. A
+    // closing maintenance window stops everything before the next step.
+    let mut failed: Vec<&'static str> = Vec::new();
+    let mut exit_code = 0;
+    let mut deferred: Option<String> = None;
+    let mut run_step = |backend: &dyn PackageBackend, cmd: Vec<String>, description: String| {
+        if failed.contains(&backend.name()) || deferred.is_some() || interrupted().is_some() {
+            return;
+        }
+        if let Err(reason) = windows.check() {
+            deferred = Some(reason);
+            return;
+        }
+        let mut step = run_retrying(&cmd, &description, tracked);
+        step.backend = Some(backend.name());
+        step.transactions = step_transactions(backend, &step);
+        report.warnings.extend(step_warnings(&step));
+        if !step.succeeded() {
+            failed.push(backend.name());
+            if exit_code == 0 {
+                exit_code = step.failure_code();
+            }
+        }
+        let timed_out = step.timed_out;
+        report.steps.push(step);
+        if timed_out {
+            recover_after_timeout(report);
+        }
+    };
+    // --security-only leaves backends without a security channel untouched.
+    let available: Vec<&dyn PackageBackend> = available
+        .into_iter()
+        .filter(|b| {
+            let keep = !opts.security_only || b.has_security_channel();
+            if !keep {
+                color_print!(YELLOW, "⏭  {} has no security channel; skipped\n", b.name());
+            }
+            keep
+        })
+        .collect();
+    let mut notes: Vec<String> = Vec::new();
+    let mut downloads: Vec<(Vec<String>, u64)> = Vec::new();
+    for backend in &available {
+        let name = backend.name();
+        if let Some(cmd) = backend.refresh() {
+            run_step(*backend, cmd, format!("Refreshing {name} metadata …"));
+        }
+        if !opts.security_only {
+            if let Some(note) = backend.upgrade_blocked() {
+                color_print!(YELLOW, "⚠️  {}\n", note);
+                notes.push(note);
+                continue;
+            }
+            // Asked before upgrading, while the updates are still pending.
+            if let Some(bytes) = backend.download_size() {
+                downloads.push((backend.upgrade(), bytes));
+            }
+            run_step(
+                *backend,
+                backend.upgrade(),
+                format!("Upgrading {name} packages …"),
+            );
+            continue;
+        }
+        match backend.list_security_upgrades() {
+            Ok(changes) if changes.is_empty() => {
+                color_print!(GREEN, "✅ No {} security updates\n\n", name)
+            }
+            Ok(changes) => {
+                let names: Vec<String> = changes.into_iter().map(|c| c.name).collect();
+                let description = format!("Installing {} {name} security update(s) …", names.len());
+                run_step(*backend, backend.upgrade_packages(&names), description);
+            }
+            // Recorded once run_step no longer holds the report.
+            Err(note) => {
+                color_print!(YELLOW, "⚠️  {}\n", note);
+                notes.push(note);
+            }
+        }
+    }
+    for backend in available.iter().filter(|_| !opts.no_cleanup) {
+        if let Some(cmd) = backend.cleanup() {
+            let description = format!("Purging orphaned {} packages …", backend.name());
+            run_step(*backend, cmd, description);
+        }
+    }
+
+    report.warnings.append(&mut notes);
+    report.downloaded = downloads
+        .iter()
+        .filter(|(cmd, _)| report.steps.iter().any(|s| s.argv == *cmd && s.succeeded()))
+        .map(|(_, bytes)| bytes)
+        .sum();
+    record_held_back(report, &available);
+    let changes = net_changes(&report.steps);
+    if opts.changelogs {
+        report.changelogs = collect_changelogs(&changes);
+        print_changelogs(&report.changelogs);
+    }
+    let feed = match opts.cve_feed.as_deref().map(load_cve_feed).transpose() {
+        Ok(feed) => feed,
+        Err(msg) => {
+            report.warn(format!("Cannot read CVE feed: {msg}"));
+            None
+        }
+    };
+    report.cves = find_cves(&changes, feed.as_ref());
+    if report.cves.iter().any(|f| !f.cves.is_empty()) {
+        color_print!(format!("{MAGENTA}{BOLD}"), "\n🛡  CVEs fixed by this run\n");
+        print_cves(report.cves.iter().filter(|f| !f.cves.is_empty()));
+    }
+
+    // Nothing further runs once interrupted, not even post-update hooks.
+    if let Some(signum) = interrupted() {
+        return interrupt(report, opts, signum);
+    }
+    // Restarts and reboots are disruptive, so they wait for a window too.
+    if deferred.is_none() {
+        handle_stale_services(report, opts);
+        handle_reboot(report, opts);
+    }
+
+    let outcome = match (&deferred, exit_code) {
+        (_, code) if code != 0 => "failed",
+        (Some(_), _) => "deferred",
+        (None, _) => "success",
+    };
+    if run_hooks(report, &opts.post_hooks, "post-update", Some(outcome)).is_err() {
+        report.warn("A post-update hook failed".into());
+    }
+
+    print_summary(report);
+
+    if exit_code != 0 {
+        return abort(report, opts, exit_code);
+    }
+    if let Some(reason) = deferred {
+        return defer(report, opts, &reason);
+    }
+    color_print!(GREEN, "✅ Rhino Linux is up-to-date and squeaky-clean!\n");
+    report.finish("success", 0);
+    emit_report(report, opts);
+    0
+}
@@ -0,0 +1,310 @@
+use std::path::Path;
+use std::time::SystemTime;
+
+use crate::cli::Options;
+use crate::exec::capture;
+use crate::time::{civil_from_days, unix_secs};
+
+// Maintenance windows ---------------------------------------------------------
+// `--window "Mon-Fri 22:00-06:00"` (repeatable) limits when an update may run;
+// a range that ends before it starts continues into the next day. Blackout
+// dates (YYYY-MM-DD) close every window for that day. Times are read in
+// `--timezone` (local, UTC, +HH:MM or a zoneinfo name such as Europe/Berlin).
+// Outside the windows the run stops before its next step and exits with
+// EXIT_DEFERRED (EX_TEMPFAIL), which the timer service treats as success.
+pub const EXIT_DEFERRED: i32 = 75;
+pub(crate) const WEEKDAYS: [&str; 7] = ["Mon", "Tue", "Wed", "Thu", "Fri", "Sat", "Sun"];
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Window {
+    pub(crate) spec: String,
+    pub(crate) days: [bool; 7],
+    pub(crate) start: u32,
+    pub(crate) end: u32,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub enum TimeZone {
+    #[default]
+    Local,
+    Fixed(i64),
+    Named(String),
+}
+
+pub(crate) fn parse_weekday(name: &str) -> Option<usize> {
+    let prefix = name.get(..3)?;
+    WEEKDAYS
+        .iter()
+        .position(|day| day.eq_ignore_ascii_case(prefix))
+}
+
+pub(crate) fn parse_minutes(time: &str) -> Option<u32> {
+    let (h, m) = time.split_once(':')?;
+    let (h, m) = (h.parse::<u32>().ok()?, m.parse::<u32>().ok()?);
+    (h <= 24 && m < 60 && h * 60 + m <= 24 * 60).then_some(h * 60 + m)
+}
+
+pub(crate) fn parse_window(spec: &str) -> Result<Window, String> {
+    let invalid =
+        || format!("Invalid maintenance window: {spec} (expected e.g. \"Mon-Fri 22:00-06:00\")");
+    let (days_spec, range) = match spec.trim().split_once(char::is_whitespace) {
+        Some((days, range)) => (days, range.trim()),
+        None => ("*", spec.trim()),
+    };
+    let mut days = [false; 7];
+    for part in days_spec.split(',') {
+        match part.split_once('-') {
+            _ if part == "*" || part.eq_ignore_ascii_case("daily") => days = [true; 7],
+            Some((from, to)) => {
+                let (from, to) = (
+                    parse_weekday(from).ok_or_else(invalid)?,
+                    parse_weekday(to).ok_or_else(invalid)?,
+                );
+                let mut day = from;
+                loop {
+                    days[day] = true;
+                    if day == to {
+                        break;
+                    }
+                    day = (day + 1) % 7;
+                }
+            }
+            None => days[parse_weekday(part).ok_or_else(invalid)?] = true,
+        }
+    }
+    let (start, end) = range.split_once('-').ok_or_else(invalid)?;
+    Ok(Window {
+        spec: spec.trim().to_string(),
+        days,
+        start: parse_minutes(start).ok_or_else(invalid)?,
+        end: parse_minutes(end).ok_or_else(invalid)?,
+    })
+}
+
+pub(crate) fn parse_timezone(tz: &str) -> Result<TimeZone, String> {
+    if tz.eq_ignore_ascii_case("local") {
+        return Ok(TimeZone::Local);
+    }
+    if tz.eq_ignore_ascii_case("UTC") || tz == "Z" {
+        return Ok(TimeZone::Fixed(0));
+    }
+    if let Some(sign) = tz
+        .strip_prefix(['+', '-'])
+        .map(|_| if tz.starts_with('-') { -1 } else { 1 })
+    {
+        return parse_minutes(&tz[1..])
+            .map(|m| TimeZone::Fixed(sign * i64::from(m) * 60))
+            .ok_or_else(|| format!("Invalid UTC offset: {tz} (expected +HH:MM)"));
+    }
+    // glibc silently falls back to UTC for unknown zones, so check first.
+    if !tz.contains("..") && Path::new("/usr/share/zoneinfo").join(tz).is_file() {
+        Ok(TimeZone::Named(tz.to_string()))
+    } else {
+        Err(format!("Unknown time zone: {tz}"))
+    }
+}
+
+// Seconds east of UTC right now, asked of date(1) so DST is handled.
+pub(crate) fn utc_offset(tz: &TimeZone) -> Result<i64, String> {
+    let zone;
+    let cmd = match tz {
+        TimeZone::Fixed(offset) => return Ok(*offset),
+        TimeZone::Named(name) => {
+            zone = format!("TZ={name}");
+            vec!["env", zone.as_str(), "date", "+%z"]
+        }
+        TimeZone::Local => vec!["date", "+%z"],
+    };
+    let text = capture(&cmd).ok_or("date failed")?;
+    let text = text.trim();
+    match (text.get(..1), text.get(1..3), text.get(3..5)) {
+        (Some(sign), Some(h), Some(m)) => {
+            let secs = h.parse::<i64>().map_err(|_| format!("date: {text}"))? * 3600
+                + m.parse::<i64>().map_err(|_| format!("date: {text}"))? * 60;
+            Ok(if sign == "-" { -secs } else { secs })
+        }
+        _ => Err(format!("date printed no UTC offset: {text}")),
+    }
+}
+
+impl Window {
+    // `weekday` is 0 for Monday; `minute` counts from local midnight.
+    pub fn contains(&self, weekday: usize, minute: u32) -> bool {
+        let yesterday = (weekday + 6) % 7;
+        if self.start < self.end {
+            self.days[weekday] && (self.start..self.end).contains(&minute)
+        } else if self.start > self.end {
+            (self.days[weekday] && minute >= self.start)
+                || (self.days[yesterday] && minute < self.end)
+        } else {
+            self.days[weekday]
+        }
+    }
+}
+
+// The window checks of one run. The time zone's UTC offset is read on the
+// first check and kept, so a run checks before every step without asking
+// date(1) each time.
+pub struct WindowCheck<'a> {
+    opts: &'a Options,
+    offset: Option<i64>,
+}
+
+impl<'a> WindowCheck<'a> {
+    pub fn new(opts: &'a Options) -> Self {
+        WindowCheck { opts, offset: None }
+    }
+
+    // Ok when an update may run now, otherwise why not.
+    pub fn check(&mut self) -> Result<(), String> {
+        let opts = self.opts;
+        if opts.ignore_windows || (opts.windows.is_empty() && opts.blackouts.is_empty()) {
+            return Ok(());
+        }
+        let offset = match self.offset {
+            Some(offset) => offset,
+            None => {
+                let offset = utc_offset(&opts.timezone)
+                    .map_err(|e| format!("cannot read the time zone: {e}"))?;
+                *self.offset.insert(offset)
+            }
+        };
+        let local = unix_secs(SystemTime::now()) as i64 + offset;
+        check_local_time(&opts.windows, &opts.blackouts, local)
+    }
+}
+
+// `local` counts seconds since 1970-01-01 00:00 in the windows' time zone.
+pub(crate) fn check_local_time(
+    windows: &[Window],
+    blackouts: &[String],
+    local: i64,
+) -> Result<(), String> {
+    let days = local.div_euclid(86_400);
+    let minute = (local.rem_euclid(86_400) / 60) as u32;
+    // 1970-01-01 was a Thursday.
+    let weekday = (days + 3).rem_euclid(7) as usize;
+    let (y, m, d) = civil_from_days(days);
+    let today = format!("{y:04}-{m:02}-{d:02}");
+    let now = format!(
+        "{} {:02}:{:02}",
+        WEEKDAYS[weekday],
+        minute / 60,
+        minute % 60
+    );
+    if blackouts.contains(&today) {
+        return Err(format!("{today} is a blackout date"));
+    }
+    if windows.is_empty() || windows.iter().any(|w| w.contains(weekday, minute)) {
+        return Ok(());
+    }
+    let specs: Vec<&str> = windows.iter().map(|w| w.spec.as_str()).collect();
+    Err(format!("{now} is outside {}", specs.join(", ")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::exec::testing::{install_fake, FakeRunner};
+
+    // Local time on a day counted from 1970-01-01.
+    fn at(days: i64, time: &str) -> i64 {
+        days * 86_400 + i64::from(parse_minutes(time).unwrap()) * 60
+    }
+
+    const MON_2024_03_04: i64 = 19_786;
+    const SAT_2024_03_09: i64 = 19_791;
+    const SUN_2024_03_10: i64 = 19_792;
+
+    #[test]
+    fn windows_parse_days_and_ranges() {
+        let window = parse_window("Mon-Fri 22:00-06:00").unwrap();
+        assert_eq!(window.days, [true, true, true, true, true, false, false]);
+        assert_eq!((window.start, window.end), (22 * 60, 6 * 60));
+        // Day ranges wrap around the week.
+        let weekend = parse_window("sat-mon 00:00-24:00").unwrap();
+        assert_eq!(weekend.days, [true, false, false, false, false, true, true]);
+        assert_eq!(parse_window("02:00-04:00").unwrap().days, [true; 7]);
+        assert_eq!(
+            parse_window("Tue,Thu 01:00-02:00").unwrap().days[1..4],
+            [true, false, true]
+        );
+        for bad in [
+            "Mon-Fri",
+            "Mon 22:00",
+            "Funday 01:00-02:00",
+            "Mon 25:00-26:00",
+            "Mon 1:60-2:00",
+        ] {
+            assert!(parse_window(bad).is_err(), "{bad}");
+        }
+    }
+
+    #[test]
+    fn windows_contain_their_days_and_times() {
+        let night = parse_window("Mon-Fri 22:00-06:00").unwrap();
+        // An overnight window runs into the next day, whatever that is.
+        assert!(night.contains(4, 23 * 60));
+        assert!(night.contains(5, 5 * 60 + 59));
+        assert!(!night.contains(5, 6 * 60));
+        assert!(!night.contains(5, 23 * 60));
+        // Sunday night belongs to no window; early Monday belongs to Sunday's.
+        assert!(!night.contains(0, 60));
+        assert!(night.contains(0, 22 * 60));
+        let day = parse_window("Sat 09:00-17:00").unwrap();
+        assert!(day.contains(5, 9 * 60));
+        assert!(!day.contains(5, 17 * 60));
+        assert!(!day.contains(6, 10 * 60));
+        // Equal ends mean the whole day.
+        assert!(parse_window("Sun 00:00-00:00")
+            .unwrap()
+            .contains(6, 12 * 60));
+    }
+
+    #[test]
+    fn local_time_is_checked_against_windows_and_blackouts() {
+        let windows = [parse_window("Mon-Fri 22:00-06:00").unwrap()];
+        assert_eq!(
+            check_local_time(&windows, &[], at(MON_2024_03_04, "23:30")),
+            Ok(())
+        );
+        assert_eq!(
+            check_local_time(&windows, &[], at(SUN_2024_03_10, "23:30")),
+            Err("Sun 23:30 is outside Mon-Fri 22:00-06:00".into())
+        );
+        // Friday night's window reaches into Saturday morning.
+        assert_eq!(
+            check_local_time(&windows, &[], at(SAT_2024_03_09, "05:59")),
+            Ok(())
+        );
+        let blackouts = ["2024-03-04".to_string()];
+        assert_eq!(
+            check_local_time(&windows, &blackouts, at(MON_2024_03_04, "23:30")),
+            Err("2024-03-04 is a blackout date".into())
+        );
+        // Blackouts apply without windows too.
+        assert!(check_local_time(&[], &blackouts, at(MON_2024_03_04, "12:00")).is_err());
+        assert_eq!(
+            check_local_time(&[], &blackouts, at(SAT_2024_03_09, "12:00")),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn the_offset_is_read_once_per_run() {
+        let fake = install_fake(
+            FakeRunner::default().on(&["env", "TZ=Asia/Kolkata", "date"], &[(0, "+0530\n")]),
+        );
+        let opts = Options {
+            windows: vec![parse_window("00:00-00:00").unwrap()],
+            timezone: TimeZone::Named("Asia/Kolkata".into()),
+            ..Options::default()
+        };
+        let mut windows = WindowCheck::new(&opts);
+        assert_eq!(windows.check(), Ok(()));
+        assert_eq!(windows.check(), Ok(()));
+        assert_eq!(windows.offset, Some(5 * 3600 + 30 * 60));
+        assert_eq!(fake.calls(), ["env TZ=Asia/Kolkata date +%z"]);
+        assert_eq!(utc_offset(&TimeZone::Fixed(-3600)), Ok(-3600));
+    }
+}
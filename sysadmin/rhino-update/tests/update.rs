@@ -0,0 +1,308 @@
+use rhino_update::backends::{all_backends, argv, PackageBackend, PackageChange};
+use rhino_update::cli::{configure, parse_args, Options};
+use rhino_update::exec::{set_runner, CommandRunner, StepOutput, SystemRunner};
+use rhino_update::json::Json;
+use rhino_update::rollback::{cmd_rollback, recorded_net_changes};
+use rhino_update::store::{read_run, runs_dir};
+use rhino_update::update::cmd_update;
+use std::env;
+use std::fs;
+use std::io;
+use std::os::unix::fs::PermissionsExt;
+use std::path::PathBuf;
+use std::process::Output;
+use std::rc::Rc;
+
+// The whole update flow against stub programs on a private PATH. The stubs
+// log their calls to `calls` in the stub directory; installing upgrades flips
+// the version dpkg-query reports.
+const APT_GET_STUB: &str = r#"#!/bin/sh
+echo "apt-get $*" >> "$STUBS/calls"
+case "$*" in
+*"-s -o Debug::NoLocking=1 dist-upgrade")
+  [ -e "$STUBS/upgraded" ] ||
+echo "Inst hello [2.10-3] (2.10-3ubuntu1 Ubuntu:24.04/noble-updates [amd64])" ;;
+*"-s "*) ;;
+*" dist-upgrade")
+  [ -e "$STUBS/fail-upgrade" ] && { echo "E: Sub-process /usr/bin/dpkg returned an error code (1)" >&2; exit 100; }
+  echo "Unpacking hello (2.10-3ubuntu1) over (2.10-3) ..."
+  echo "Setting up hello (2.10-3ubuntu1) ..."
+  : > "$STUBS/upgraded" ;;
+esac
+exit 0
+"#;
+
+const DPKG_QUERY_STUB: &str = r#"#!/bin/sh
+version=2.10-3
+[ -e "$STUBS/upgraded" ] && version=2.10-3ubuntu1
+printf 'ii \thello\t%s\n' "$version"
+"#;
+
+const RECORDING_STUB: &str = r#"#!/bin/sh
+echo "${0##*/} $*" >> "$STUBS/calls"
+"#;
+
+// Stubs change nothing, so they may stand in for root.
+struct StubRunner(SystemRunner);
+
+impl CommandRunner for StubRunner {
+    fn run(&self, cmd: &[String]) -> io::Result<StepOutput> {
+        self.0.run(cmd)
+    }
+
+    fn output(&self, cmd: &[String]) -> io::Result<Output> {
+        self.0.output(cmd)
+    }
+
+    fn exists(&self, program: &str) -> bool {
+        self.0.exists(program)
+    }
+
+    fn is_root(&self) -> bool {
+        true
+    }
+}
+
+struct StubSystem {
+    dir: PathBuf,
+}
+
+impl StubSystem {
+    fn new(name: &str) -> Self {
+        let dir = env::temp_dir().join(format!("rhino-update-{}-{name}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let bin = dir.join("bin");
+        fs::create_dir_all(&bin).unwrap();
+        let stubs = [
+            ("apt-get", APT_GET_STUB),
+            ("dpkg-query", DPKG_QUERY_STUB),
+            ("dpkg", RECORDING_STUB),
+            ("rpk", RECORDING_STUB),
+            ("timeshift", RECORDING_STUB),
+        ];
+        for (program, script) in stubs {
+            let script = script.replace("$STUBS", &dir.display().to_string());
+            let path = bin.join(program);
+            fs::write(&path, script).unwrap();
+            fs::set_permissions(&path, fs::Permissions::from_mode(0o755)).unwrap();
+        }
+        set_runner(Rc::new(StubRunner(SystemRunner {
+            path: Some(bin.into_os_string()),
+        })));
+        env::set_var(
+            "RHINO_UPDATE_STATE_DIR",
+            env::temp_dir().join("rhino-update-state"),
+        );
+        StubSystem { dir }
+    }
+
+    // Run `rhino-update ARGS` and return its exit code and JSON report.
+    fn update(&self, args: &[&str]) -> (i32, Json) {
+        self.update_with(args, &all_backends())
+    }
+
+    fn update_with(&self, args: &[&str], backends: &[Box<dyn PackageBackend>]) -> (i32, Json) {
+        self.run(args, |opts| cmd_update(opts, backends).exit_code())
+    }
+
+    // The same for `rhino-update rollback RUN_ID ARGS`.
+    fn rollback(&self, run_id: &str, args: &[&str]) -> (i32, Json) {
+        self.run(args, |opts| cmd_rollback(opts, Some(run_id)).exit_code())
+    }
+
+    fn run(&self, args: &[&str], command: impl FnOnce(&Options) -> i32) -> (i32, Json) {
+        let report = self.dir.join("report.json");
+        let log = self.dir.join("log");
+        let mut cli = vec![
+            "--skip-preflight".to_string(),
+            "--retry-delay=0".to_string(),
+            format!("--log-file={}", log.display()),
+            format!("--report-file={}", report.display()),
+        ];
+        cli.extend(args.iter().map(|a| a.to_string()));
+        let mut opts = Options::default();
+        parse_args(&mut opts, cli.into_iter()).unwrap();
+        configure(&opts);
+        let code = command(&opts);
+        (code, read_run(&report).unwrap())
+    }
+
+    fn calls(&self) -> Vec<String> {
+        let calls = fs::read_to_string(self.dir.join("calls")).unwrap_or_default();
+        calls.lines().map(str::to_string).collect()
+    }
+}
+
+impl Drop for StubSystem {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.dir);
+    }
+}
+
+fn step_commands(report: &Json) -> Vec<String> {
+    let steps = report.get("steps").as_arr();
+    steps
+        .iter()
+        .map(|step| {
+            let argv = step.get("argv").as_arr().iter();
+            argv.filter_map(Json::as_str).collect::<Vec<_>>().join(" ")
+        })
+        .collect()
+}
+
+#[test]
+fn update_upgrades_cleans_up_and_records_changes() {
+    let system = StubSystem::new("update");
+    let (code, report) = system.update(&[]);
+    assert_eq!(code, 0);
+    assert_eq!(report.get("outcome").as_str(), Some("success"));
+    assert_eq!(
+        step_commands(&report),
+        [
+            "apt-get update",
+            "apt-get -y -o Dpkg::Options::=--force-confdef \
+             -o Dpkg::Options::=--force-confold dist-upgrade",
+            "apt-get -y autoremove --purge",
+        ]
+    );
+    let changes = recorded_net_changes(&report);
+    assert_eq!(changes.len(), 1);
+    assert_eq!(changes[0].name, "hello");
+    assert_eq!(changes[0].old_version.as_deref(), Some("2.10-3"));
+    assert_eq!(changes[0].new_version.as_deref(), Some("2.10-3ubuntu1"));
+    // The upgrade step's own output tells the same story.
+    let upgrade = &report.get("steps").as_arr()[1];
+    let transactions = upgrade.get("transactions").as_arr();
+    assert_eq!(transactions.len(), 1);
+    assert_eq!(transactions[0].get("action").as_str(), Some("upgrade"));
+    assert_eq!(transactions[0].get("old_version").as_str(), Some("2.10-3"));
+    // Examined for CVEs, though no changelog is available offline here.
+    let cves = report.get("cves").as_arr();
+    assert_eq!(cves.len(), 1);
+    assert_eq!(cves[0].get("name").as_str(), Some("hello"));
+    // rpk is only ever detected as a competing package manager, not run.
+    assert!(!system.calls().iter().any(|c| c.starts_with("rpk")));
+}
+
+#[test]
+fn failed_upgrade_skips_cleanup_and_exits_with_its_code() {
+    let system = StubSystem::new("failed");
+    fs::write(system.dir.join("fail-upgrade"), "").unwrap();
+    let (code, report) = system.update(&[]);
+    assert_eq!(code, 100);
+    assert_eq!(report.get("outcome").as_str(), Some("failed"));
+    assert_eq!(report.get("exit_code").as_f64(), Some(100.0));
+    let commands = step_commands(&report);
+    assert_eq!(commands.len(), 2);
+    assert!(!system.calls().iter().any(|c| c.contains("autoremove")));
+    assert!(recorded_net_changes(&report).is_empty());
+}
+
+#[test]
+fn security_only_installs_just_the_security_upgrades() {
+    let system = StubSystem::new("security");
+    let simulation = system.dir.join("bin/apt-get");
+    let stub = fs::read_to_string(&simulation)
+        .unwrap()
+        .replace("noble-updates", "noble-security");
+    fs::write(&simulation, stub).unwrap();
+    let (code, report) = system.update(&["--security-only", "--no-cleanup"]);
+    assert_eq!(code, 0);
+    assert_eq!(
+        step_commands(&report).last().map(String::as_str),
+        Some(
+            "apt-get -y -o Dpkg::Options::=--force-confdef \
+             -o Dpkg::Options::=--force-confold install --only-upgrade hello"
+        )
+    );
+}
+
+#[test]
+fn rollback_restores_the_snapshot_only_when_asked() {
+    let system = StubSystem::new("rollback");
+    let (_, record) = system.update(&[]);
+    let run_id = record.get("run_id").as_str().unwrap().to_string();
+    // Without apt-cache the previous hello cannot be installed again; a
+    // snapshot can still bring the whole system back.
+    let path = runs_dir().join(format!("{run_id}.json"));
+    let text = fs::read_to_string(&path).unwrap();
+    let snapshot = r#""snapshot": {"kind": "timeshift", "name": "2026-10-16_03-00-00"}"#;
+    let with_snapshot = text.replace(r#""snapshot": null"#, snapshot);
+    assert_ne!(text, with_snapshot);
+    // Rewritten before each rollback, whose own record may share the ID
+    // (timestamp and process) within the same second.
+    fs::write(&path, &with_snapshot).unwrap();
+    let (code, report) = system.rollback(&run_id, &["--non-interactive"]);
+    assert_eq!(code, 1);
+    assert_eq!(report.get("outcome").as_str(), Some("failed"));
+    assert!(!system.calls().iter().any(|c| c.starts_with("timeshift")));
+
+    fs::write(&path, &with_snapshot).unwrap();
+    let (code, report) = system.rollback(&run_id, &["--non-interactive", "--restore-snapshot"]);
+    assert_eq!(code, 0);
+    assert_eq!(report.get("outcome").as_str(), Some("success"));
+    assert!(system
+        .calls()
+        .iter()
+        .any(|c| c.starts_with("timeshift --restore --snapshot 2026-10-16_03-00-00")));
+}
+
+// pacstall with a package held; its upgrade would be a harmless `sh`.
+struct HeldPacstall;
+
+impl PackageBackend for HeldPacstall {
+    fn name(&self) -> &'static str {
+        "pacstall"
+    }
+
+    fn binary(&self) -> &'static str {
+        "sh"
+    }
+
+    fn is_available(&self) -> bool {
+        true
+    }
+
+    fn refresh(&self) -> Option<Vec<String>> {
+        None
+    }
+
+    fn upgrade(&self) -> Vec<String> {
+        argv(&["sh", "-c", "exit 0"])
+    }
+
+    fn upgrade_blocked(&self) -> Option<String> {
+        Some("pacstall cannot skip held ones (neofetch)".into())
+    }
+
+    fn upgrade_packages(&self, _packages: &[String]) -> Vec<String> {
+        self.upgrade()
+    }
+
+    fn cleanup(&self) -> Option<Vec<String>> {
+        None
+    }
+
+    fn list_upgradable(&self) -> Result<Vec<PackageChange>, String> {
+        Ok(Vec::new())
+    }
+
+    fn list_orphans(&self) -> Result<Vec<PackageChange>, String> {
+        Ok(Vec::new())
+    }
+}
+
+#[test]
+fn a_blocked_upgrade_is_skipped_with_a_warning() {
+    let system = StubSystem::new("blocked");
+    let mut backends = all_backends();
+    backends.retain(|b| b.name() == "apt");
+    backends.push(Box::new(HeldPacstall));
+    let (code, report) = system.update_with(&[], &backends);
+    assert_eq!(code, 0);
+    assert!(!step_commands(&report).iter().any(|c| c.starts_with("sh")));
+    let warnings = report.get("warnings").as_arr();
+    assert!(warnings
+        .iter()
+        .any(|w| w.as_str() == Some("pacstall cannot skip held ones (neofetch)")));
+}